@@ -0,0 +1,304 @@
+//! The `#[attempt]` attribute of the [attempt](https://docs.rs/attempt) crate, which is
+//! re-exported there behind the `macros` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    Error, Expr, ExprLit, FnArg, ItemFn, Lit, LitStr, Meta, Pat, PatIdent, ReturnType, Token, Type,
+};
+
+/// Makes a function retry its body with an `Attempt`, configured by the arguments of the
+/// attribute.
+///
+/// The body is run again, with fresh clones of the arguments, until it returns [`Ok`] or one of
+/// the limits is reached. Both synchronous and `async` functions are supported, using
+/// `Attempt::run` and `Attempt::run_async` respectively.
+///
+/// The following arguments are accepted, all optional:
+/// * `max_tries = 5`: the maximum number of tries, see `Attempt::max_tries`.
+/// * `delay = "200ms"`: the delay before the first retry, see `Attempt::delay`.
+/// * `growth = 2.0`: the factor the delay is multiplied by after each retry, see
+///   `Attempt::delay_growth_magnitude`.
+/// * `max_delay = "5s"`: the cap on every delay, see `Attempt::max_delay`.
+/// * `deadline = "1m"`: the time after which no more attempts are made, see
+///   `Attempt::deadline`.
+///
+/// Durations are written like `"500ms"` or `"1m 30s"`. Other settings keep the defaults of
+/// `Attempt::to`.
+///
+/// # Example
+/// ```rust
+/// # use attempt::attempt;
+/// # use std::cell::Cell;
+/// #[attempt(max_tries = 5, delay = "1ms", growth = 2.0)]
+/// fn fetch(calls: &Cell<u32>, path: String) -> Result<String, String> {
+///     calls.set(calls.get() + 1);
+///     if calls.get() < 3 {
+///         return Err(format!("{} is unavailable", path));
+///     }
+///
+///     Ok(format!("contents of {}", path))
+/// }
+///
+/// let calls = Cell::new(0);
+/// assert_eq!(fetch(&calls, "/index.html".into()).as_deref(), Ok("contents of /index.html"));
+/// assert_eq!(calls.get(), 3);
+/// ```
+///
+/// Async functions can use `?` and `.await` as usual:
+/// ```rust
+/// # use attempt::attempt;
+/// # use std::sync::atomic::{AtomicU32, Ordering};
+/// async fn get(calls: &AtomicU32) -> Result<u32, &'static str> {
+///     let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+///     if call < 2 { Err("unavailable") } else { Ok(call) }
+/// }
+///
+/// #[attempt(max_tries = 3)]
+/// async fn fetch(calls: &AtomicU32) -> Result<u32, &'static str> {
+///     let call = get(calls).await?;
+///     Ok(call * 10)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let calls = AtomicU32::new(0);
+/// assert_eq!(fetch(&calls).await, Ok(20));
+/// # }
+/// ```
+///
+/// Arguments must be cloneable, so mutable references are rejected:
+/// ```compile_fail
+/// # use attempt::attempt;
+/// #[attempt(max_tries = 3)]
+/// fn push(items: &mut Vec<u32>) -> Result<(), ()> {
+///     items.push(1);
+///     Ok(())
+/// }
+/// ```
+///
+/// So are functions without a return type:
+/// ```compile_fail
+/// # use attempt::attempt;
+/// #[attempt]
+/// fn log(message: String) {
+///     println!("{}", message);
+/// }
+/// ```
+///
+/// And methods taking `self` other than by shared reference:
+/// ```compile_fail
+/// # use attempt::attempt;
+/// struct Counter(u32);
+///
+/// impl Counter {
+///     #[attempt]
+///     fn increment(&mut self) -> Result<u32, ()> {
+///         self.0 += 1;
+///         Ok(self.0)
+///     }
+/// }
+/// ```
+///
+/// The exact errors are checked by the tests in `tests/ui`.
+#[proc_macro_attribute]
+pub fn attempt(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = match Punctuated::<Meta, Token![,]>::parse_terminated.parse(args) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let func = syn::parse_macro_input!(item as ItemFn);
+
+    expand(args, func)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(args: Punctuated<Meta, Token![,]>, func: ItemFn) -> syn::Result<TokenStream2> {
+    let config = config(args)?;
+    let ItemFn {
+        attrs,
+        vis,
+        mut sig,
+        block,
+    } = func;
+
+    if let Some(constness) = sig.constness {
+        return Err(Error::new_spanned(
+            constness,
+            "#[attempt] can't be used on a `const fn`",
+        ));
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(
+            variadic,
+            "#[attempt] can't be used on a variadic function",
+        ));
+    }
+
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) => ty.clone(),
+        ReturnType::Default => {
+            return Err(Error::new(
+                sig.paren_token.span.close(),
+                "#[attempt] can only be used on functions returning a `Result`",
+            ))
+        }
+    };
+
+    let mut clones = Vec::new();
+    for (i, input) in sig.inputs.iter_mut().enumerate() {
+        let arg = match input {
+            FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() || receiver.mutability.is_some() {
+                    return Err(Error::new_spanned(
+                        receiver,
+                        "#[attempt] only supports methods taking `&self`, since `self` is \
+                         reused by every attempt",
+                    ));
+                }
+
+                continue;
+            }
+            FnArg::Typed(arg) => arg,
+        };
+
+        if let Type::Reference(reference) = &*arg.ty {
+            if reference.mutability.is_some() {
+                return Err(Error::new_spanned(
+                    &arg.ty,
+                    "#[attempt] can't clone a mutable reference for every attempt",
+                ));
+            }
+        }
+
+        // Every attempt gets its own clone of the argument, bound to the original pattern.
+        let pat = arg.pat.clone();
+        let name = match &*arg.pat {
+            Pat::Ident(PatIdent {
+                by_ref: None,
+                subpat: None,
+                ident,
+                ..
+            }) => ident.clone(),
+            _ => format_ident!("__attempt_arg{}", i),
+        };
+
+        *arg.pat = Pat::Ident(PatIdent {
+            attrs: Vec::new(),
+            by_ref: None,
+            mutability: None,
+            ident: name.clone(),
+            subpat: None,
+        });
+        clones.push(quote_spanned! {pat.span()=>
+            #[allow(unused_mut)]
+            let #pat = ::core::clone::Clone::clone(&#name);
+        });
+    }
+
+    let run = if sig.asyncness.is_some() {
+        quote! {
+            ::attempt::Attempt::to(|| {
+                #(#clones)*
+                async move { ::core::convert::identity::<#output>(#block) }
+            })
+            #config
+            .run_async()
+            .await
+        }
+    } else {
+        quote! {
+            ::attempt::Attempt::to(|| {
+                #(#clones)*
+                #[allow(clippy::redundant_closure_call)]
+                (move || -> #output #block)()
+            })
+            #config
+            .run()
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #run
+        }
+    })
+}
+
+/// Turns the arguments of the attribute into calls to the builder methods of `Attempt`.
+fn config(args: Punctuated<Meta, Token![,]>) -> syn::Result<TokenStream2> {
+    let mut config = TokenStream2::new();
+
+    for arg in args {
+        let Meta::NameValue(arg) = arg else {
+            return Err(Error::new_spanned(
+                arg,
+                "expected an argument like `max_tries = 5`",
+            ));
+        };
+        let name = arg
+            .path
+            .get_ident()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        let value = &arg.value;
+
+        config.extend(match name.as_str() {
+            "max_tries" => quote! { .max_tries(#value) },
+            "delay" => {
+                let delay = duration(value)?;
+                quote! { .delay(#delay) }
+            }
+            "growth" => quote! { .delay_growth_magnitude(#value) },
+            "max_delay" => {
+                let max_delay = duration(value)?;
+                quote! { .max_delay(#max_delay) }
+            }
+            "deadline" => {
+                let deadline = duration(value)?;
+                quote! { .deadline(#deadline) }
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    &arg.path,
+                    "unknown argument, expected one of `max_tries`, `delay`, `growth`, \
+                     `max_delay` and `deadline`",
+                ))
+            }
+        });
+    }
+
+    Ok(config)
+}
+
+/// Parses a human-friendly duration like `"200ms"` into a `Duration` expression.
+fn duration(value: &Expr) -> syn::Result<TokenStream2> {
+    let lit = match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => lit,
+        _ => {
+            return Err(Error::new_spanned(
+                value,
+                "expected a duration like \"200ms\" or \"1m 30s\"",
+            ))
+        }
+    };
+
+    let duration = parse_duration(lit)?;
+    let (secs, nanos) = (duration.as_secs(), duration.subsec_nanos());
+
+    Ok(quote! { ::core::time::Duration::new(#secs, #nanos) })
+}
+
+fn parse_duration(lit: &LitStr) -> syn::Result<std::time::Duration> {
+    humantime::parse_duration(&lit.value())
+        .map_err(|err| Error::new(lit.span(), format!("invalid duration: {}", err)))
+}
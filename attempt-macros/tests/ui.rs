@@ -0,0 +1,7 @@
+//! Checks the errors reported by `#[attempt]` when it is misused.
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
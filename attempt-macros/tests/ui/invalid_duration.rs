@@ -0,0 +1,8 @@
+use attempt::attempt;
+
+#[attempt(delay = "200 parsecs")]
+fn fetch() -> Result<(), ()> {
+    Ok(())
+}
+
+fn main() {}
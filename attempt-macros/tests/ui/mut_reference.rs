@@ -0,0 +1,9 @@
+use attempt::attempt;
+
+#[attempt(max_tries = 3)]
+fn push(items: &mut Vec<u32>) -> Result<(), ()> {
+    items.push(1);
+    Ok(())
+}
+
+fn main() {}
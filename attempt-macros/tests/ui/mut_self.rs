@@ -0,0 +1,13 @@
+use attempt::attempt;
+
+struct Counter(u32);
+
+impl Counter {
+    #[attempt]
+    fn increment(&mut self) -> Result<u32, ()> {
+        self.0 += 1;
+        Ok(self.0)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use attempt::attempt;
+
+#[attempt]
+fn log(message: String) {
+    println!("{}", message);
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use attempt::attempt;
+
+#[attempt(max_retries = 3)]
+fn fetch() -> Result<(), ()> {
+    Ok(())
+}
+
+fn main() {}
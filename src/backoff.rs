@@ -0,0 +1,289 @@
+//! Strategies for computing the delay between attempts.
+
+use std::time::Duration;
+
+/// Computes how long to wait between consecutive attempts.
+///
+/// An [`Attempt`](crate::Attempt) consults its backoff after every failed call that is going to
+/// be retried. Returning [`None`] makes the [`Attempt`](crate::Attempt) give up and return the
+/// most recent error, even if it has tries remaining.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, Backoff};
+/// # use std::time::Duration;
+/// /// Waits 1ms, 2ms, 3ms and then gives up.
+/// struct Steps;
+///
+/// impl Backoff for Steps {
+///     fn next_delay(&mut self, attempt: usize, _elapsed: Duration) -> Option<Duration> {
+///         (attempt <= 3).then(|| Duration::from_millis(attempt as u64))
+///     }
+/// }
+///
+/// let res: Result<(), ()> = Attempt::to(|| Err(()))
+///     .backoff(Steps)
+///     .no_max_tries()
+///     .run();
+///
+/// assert!(res.is_err());
+/// ```
+pub trait Backoff {
+    /// Returns the delay to wait before the next attempt, or [`None`] to stop retrying.
+    ///
+    /// `attempt` is the number of calls that have failed so far (so it is `1` after the first
+    /// failure) and `elapsed` is the time since the first call was started.
+    fn next_delay(&mut self, attempt: usize, elapsed: Duration) -> Option<Duration>;
+}
+
+impl<B: Backoff + ?Sized> Backoff for Box<B> {
+    fn next_delay(&mut self, attempt: usize, elapsed: Duration) -> Option<Duration> {
+        (**self).next_delay(attempt, elapsed)
+    }
+}
+
+/// Multiplies `delay` by `factor`, saturating at [`Duration::MAX`] instead of panicking.
+///
+/// Products which aren't a positive number, e.g. a zero delay multiplied by an infinite factor,
+/// are [`Duration::ZERO`].
+fn saturating_mul_f64(delay: Duration, factor: f64) -> Duration {
+    let secs = delay.as_secs_f64() * factor;
+    if secs.is_nan() || secs <= 0.0 {
+        return Duration::ZERO;
+    }
+
+    Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+}
+
+/// Waits the same amount of time between every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constant {
+    delay: Duration,
+}
+
+impl Constant {
+    /// Constructs a backoff that always waits `delay`.
+    pub fn new(delay: Duration) -> Constant {
+        Constant { delay }
+    }
+}
+
+impl Backoff for Constant {
+    fn next_delay(&mut self, _attempt: usize, _elapsed: Duration) -> Option<Duration> {
+        Some(self.delay)
+    }
+}
+
+/// Increases the delay by a fixed amount after every attempt.
+///
+/// The delays produced are `initial`, `initial + increment`, `initial + 2 * increment`, and so
+/// on.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Backoff, Linear};
+/// # use std::time::Duration;
+/// let mut backoff = Linear::new(Duration::from_millis(100), Duration::from_millis(50));
+///
+/// assert_eq!(backoff.next_delay(1, Duration::ZERO), Some(Duration::from_millis(100)));
+/// assert_eq!(backoff.next_delay(3, Duration::ZERO), Some(Duration::from_millis(200)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Linear {
+    initial: Duration,
+    increment: Duration,
+}
+
+impl Linear {
+    /// Constructs a backoff that starts at `initial` and grows by `increment` each attempt.
+    pub fn new(initial: Duration, increment: Duration) -> Linear {
+        Linear { initial, increment }
+    }
+}
+
+impl Backoff for Linear {
+    fn next_delay(&mut self, attempt: usize, _elapsed: Duration) -> Option<Duration> {
+        let steps = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+
+        Some(
+            self.initial
+                .saturating_add(self.increment.saturating_mul(steps)),
+        )
+    }
+}
+
+/// Multiplies the delay by a constant factor after every attempt.
+///
+/// The delays produced are `initial`, `initial * factor`, `initial * factor^2`, and so on. This
+/// is the schedule configured by [`Attempt::delay`](crate::Attempt::delay) and
+/// [`Attempt::delay_growth_magnitude`](crate::Attempt::delay_growth_magnitude).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Backoff, Exponential};
+/// # use std::time::Duration;
+/// let mut backoff = Exponential::new(Duration::from_millis(100), 2.0);
+///
+/// assert_eq!(backoff.next_delay(1, Duration::ZERO), Some(Duration::from_millis(100)));
+/// assert_eq!(backoff.next_delay(3, Duration::ZERO), Some(Duration::from_millis(400)));
+/// ```
+///
+/// A zero initial delay stays zero however many attempts are made, so an
+/// [`Attempt`](crate::Attempt) without a delay never sleeps:
+/// ```rust
+/// # use attempt::Attempt;
+/// let mut calls = 0;
+/// let res = Attempt::to(|| {
+///     calls += 1;
+///     if calls < 5000 { Err("busy") } else { Ok(calls) }
+/// })
+/// .no_max_tries()
+/// .deadline(std::time::Duration::from_secs(60))
+/// .run();
+///
+/// assert_eq!(res, Ok(5000));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exponential {
+    initial: Duration,
+    factor: f32,
+}
+
+impl Exponential {
+    /// Constructs a backoff that starts at `initial` and is multiplied by `factor` each attempt.
+    pub fn new(initial: Duration, factor: f32) -> Exponential {
+        Exponential { initial, factor }
+    }
+
+    pub(crate) fn set_initial(&mut self, initial: Duration) {
+        self.initial = initial;
+    }
+
+    pub(crate) fn set_factor(&mut self, factor: f32) {
+        self.factor = factor;
+    }
+}
+
+impl Backoff for Exponential {
+    fn next_delay(&mut self, attempt: usize, _elapsed: Duration) -> Option<Duration> {
+        if self.initial.is_zero() {
+            return Some(Duration::ZERO);
+        }
+
+        let steps = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+
+        Some(saturating_mul_f64(
+            self.initial,
+            f64::from(self.factor).powi(steps),
+        ))
+    }
+}
+
+/// Grows the delay along the Fibonacci sequence.
+///
+/// The delays produced are `initial`, `initial`, `2 * initial`, `3 * initial`, `5 * initial`,
+/// and so on, which grows more gently than [`Exponential`] with a factor of 2.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Backoff, Fibonacci};
+/// # use std::time::Duration;
+/// let mut backoff = Fibonacci::new(Duration::from_millis(100));
+///
+/// assert_eq!(backoff.next_delay(2, Duration::ZERO), Some(Duration::from_millis(100)));
+/// assert_eq!(backoff.next_delay(5, Duration::ZERO), Some(Duration::from_millis(500)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fibonacci {
+    initial: Duration,
+}
+
+impl Fibonacci {
+    /// Constructs a backoff that starts at `initial` and follows the Fibonacci sequence.
+    pub fn new(initial: Duration) -> Fibonacci {
+        Fibonacci { initial }
+    }
+}
+
+impl Backoff for Fibonacci {
+    fn next_delay(&mut self, attempt: usize, _elapsed: Duration) -> Option<Duration> {
+        let (mut current, mut next) = (1u32, 1u32);
+        for _ in 1..attempt {
+            (current, next) = (next, current.saturating_add(next));
+        }
+
+        Some(self.initial.saturating_mul(current))
+    }
+}
+
+/// AWS-style "decorrelated jitter" backoff.
+///
+/// Each delay is picked at random between `base` and three times the previous delay, and is
+/// never larger than `cap`. Spreading the delays out this way keeps many clients that failed at
+/// the same moment from retrying in lockstep.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Backoff, DecorrelatedJitter};
+/// # use std::time::Duration;
+/// let (base, cap) = (Duration::from_millis(100), Duration::from_secs(1));
+/// let mut backoff = DecorrelatedJitter::new(base, cap);
+///
+/// for attempt in 1..20 {
+///     let delay = backoff.next_delay(attempt, Duration::ZERO).unwrap();
+///     assert!(base <= delay && delay <= cap);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecorrelatedJitter {
+    base: Duration,
+    cap: Duration,
+    previous: Duration,
+    rng: fastrand::Rng,
+}
+
+impl DecorrelatedJitter {
+    /// Constructs a backoff whose delays fall between `base` and `cap`.
+    pub fn new(base: Duration, cap: Duration) -> DecorrelatedJitter {
+        DecorrelatedJitter {
+            base,
+            cap,
+            previous: base,
+            rng: fastrand::Rng::new(),
+        }
+    }
+
+    /// Seeds the random number generator, making the produced delays deterministic.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng.seed(seed);
+
+        self
+    }
+}
+
+impl Backoff for DecorrelatedJitter {
+    fn next_delay(&mut self, _attempt: usize, _elapsed: Duration) -> Option<Duration> {
+        let delay = decorrelated(&mut self.rng, self.base, self.previous).min(self.cap);
+        self.previous = delay;
+
+        Some(delay)
+    }
+}
+
+/// Picks the next delay of a decorrelated jitter schedule: a random duration between `base` and
+/// three times the `previous` one.
+pub(crate) fn decorrelated(
+    rng: &mut fastrand::Rng,
+    base: Duration,
+    previous: Duration,
+) -> Duration {
+    random_between(rng, base, previous.saturating_mul(3).max(base))
+}
+
+/// Picks a duration uniformly at random from the inclusive range `low..=high`.
+pub(crate) fn random_between(rng: &mut fastrand::Rng, low: Duration, high: Duration) -> Duration {
+    let low_nanos = low.as_nanos().min(u64::MAX as u128) as u64;
+    let high_nanos = high.as_nanos().min(u64::MAX as u128) as u64;
+
+    Duration::from_nanos(rng.u64(low_nanos..=high_nanos.max(low_nanos)))
+}
@@ -0,0 +1,203 @@
+//! Retry budgets, to bound the extra load retries put on a dependency.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The default share of requests which can be retried.
+const DEFAULT_PERCENT: f32 = 0.2;
+
+/// The default number of retries allowed per second regardless of the number of requests.
+const DEFAULT_MIN_PER_SECOND: u32 = 10;
+
+/// The default span of time over which requests and retries are counted.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Limits the retries made by many [`Attempt`](crate::Attempt)s to a share of the requests they
+/// make, like the retry budgets of gRPC and Finagle.
+///
+/// Every run of an [`Attempt`](crate::Attempt) using the budget counts as one request, and every
+/// call after the first one as a retry. Over the last [`RetryBudget::window`], at most
+/// [`RetryBudget::percent`] of the requests can be retried, plus
+/// [`RetryBudget::min_per_second`] retries per second so that quiet clients can still retry.
+/// Once the budget is exhausted, runs give up on their first retryable error with
+/// [`StopReason::BudgetExhausted`](crate::StopReason::BudgetExhausted), no matter how many
+/// tries remain, so that a struggling dependency doesn't get flooded with retries from every
+/// client.
+///
+/// Cloning the budget yields another handle to the same state, so a single budget can be shared
+/// by every call site (and thread) talking to a dependency. It is attached to an
+/// [`Attempt`](crate::Attempt) with [`Attempt::retry_budget`](crate::Attempt::retry_budget).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, RetryBudget, StopReason};
+/// # use std::cell::Cell;
+/// let budget = RetryBudget::new().percent(0.5).min_per_second(0);
+///
+/// // Two requests, each earning half a retry.
+/// for _ in 0..2 {
+///     Attempt::to(|| Ok::<_, ()>(()))
+///         .retry_budget(budget.clone())
+///         .run()
+///         .unwrap();
+/// }
+///
+/// // Half a retry more makes a single one.
+/// let calls = Cell::new(0);
+/// let err = Attempt::to(|| {
+///     calls.set(calls.get() + 1);
+///     Err::<(), _>("unavailable")
+/// })
+/// .no_delay()
+/// .retry_budget(budget)
+/// .run_detailed()
+/// .unwrap_err();
+///
+/// assert_eq!(err.reason(), StopReason::BudgetExhausted);
+/// assert_eq!(calls.get(), 2);
+/// #
+/// # // Re-creating the source of an iterator is a retry too, and doesn't earn any.
+/// # let budget = RetryBudget::new().percent(1.0).min_per_second(0);
+/// # let sources = Cell::new(0);
+/// # let items: Vec<_> = Attempt::to(|_: Option<&()>| {
+/// #     sources.set(sources.get() + 1);
+/// #     [Err::<(), _>("unavailable")]
+/// # })
+/// # .no_delay()
+/// # .no_max_tries()
+/// # .retry_budget(budget)
+/// # .resume_iter()
+/// # .collect();
+/// # assert_eq!(items, [Err("unavailable")]);
+/// # assert_eq!(sources.get(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RetryBudget {
+    inner: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    percent: f32,
+    min_per_second: u32,
+    window: Duration,
+    created: Instant,
+
+    /// The requests and retries counted during each second of the window, oldest first.
+    slots: VecDeque<Slot>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            percent: DEFAULT_PERCENT,
+            min_per_second: DEFAULT_MIN_PER_SECOND,
+            window: DEFAULT_WINDOW,
+            created: Instant::now(),
+            slots: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    /// The number of whole seconds between the creation of the budget and this slot.
+    second: u64,
+    requests: u64,
+    retries: u64,
+}
+
+impl State {
+    /// Drops the slots which fell out of the window, and returns the one of the current second.
+    fn current(&mut self) -> &mut Slot {
+        let second = self.created.elapsed().as_secs();
+        let window = self.window.as_secs().max(1);
+
+        while self
+            .slots
+            .front()
+            .is_some_and(|slot| slot.second + window <= second)
+        {
+            self.slots.pop_front();
+        }
+
+        if self.slots.back().map(|slot| slot.second) != Some(second) {
+            self.slots.push_back(Slot {
+                second,
+                requests: 0,
+                retries: 0,
+            });
+        }
+
+        self.slots.back_mut().unwrap()
+    }
+}
+
+impl RetryBudget {
+    /// Constructs a new budget which allows retrying 20% of the requests made over the last 10
+    /// seconds, plus 10 retries per second.
+    pub fn new() -> RetryBudget {
+        RetryBudget::default()
+    }
+
+    /// Sets the share of requests, between 0 and 1 (inclusive), which can be retried. Checked by
+    /// assertion.
+    pub fn percent(self, percent: f32) -> Self {
+        assert!((0.0..=1.0).contains(&percent));
+        self.lock().percent = percent;
+
+        self
+    }
+
+    /// Sets the number of retries allowed per second on top of [`RetryBudget::percent`], so
+    /// that clients making few requests can still retry.
+    pub fn min_per_second(self, retries: u32) -> Self {
+        self.lock().min_per_second = retries;
+
+        self
+    }
+
+    /// Sets the span of time over which requests and retries are counted, which is rounded down
+    /// to whole seconds (but at least one).
+    pub fn window(self, window: Duration) -> Self {
+        self.lock().window = window;
+
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Counts a new request, which earns [`RetryBudget::percent`] of a retry.
+    pub(crate) fn deposit(&self) {
+        self.lock().current().requests += 1;
+    }
+
+    /// Takes a retry out of the budget, returning `false` if none is left.
+    pub(crate) fn try_withdraw(&self) -> bool {
+        let mut state = self.lock();
+        state.current();
+
+        let window = state.window.as_secs().max(1);
+        let (requests, retries) = state
+            .slots
+            .iter()
+            .fold((0, 0), |(requests, retries), slot| {
+                (requests + slot.requests, retries + slot.retries)
+            });
+        let reserve = state.min_per_second as f32 * window as f32;
+        let balance = reserve + state.percent * requests as f32 - retries as f32;
+
+        if balance < 1.0 {
+            return false;
+        }
+
+        state.current().retries += 1;
+
+        true
+    }
+}
@@ -0,0 +1,177 @@
+//! Cancellation of retry loops from the outside.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+#[cfg(feature = "async-core")]
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A handle used to stop one or more running [`Attempt`](crate::Attempt)s, e.g. during a
+/// graceful shutdown.
+///
+/// Cloning the token yields another handle to the same cancellation state. Once
+/// [`CancelToken::cancel`] is called, every [`Attempt`](crate::Attempt) given the token with
+/// [`Attempt::cancel_token`](crate::Attempt::cancel_token) stops at its next opportunity: a
+/// sleeping retry loop wakes up immediately, and no further attempts are made. The call in
+/// progress when the token is cancelled, if any, is allowed to complete.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, CancelToken, StopReason};
+/// # use std::time::{Duration, Instant};
+/// let token = CancelToken::new();
+/// let canceller = token.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(50));
+///     canceller.cancel();
+/// });
+///
+/// let started = Instant::now();
+/// let err = Attempt::to(|| Err::<(), _>("unavailable"))
+///     .delay(Duration::from_secs(3600))
+///     .cancel_token(token)
+///     .run_detailed()
+///     .unwrap_err();
+///
+/// assert_eq!(err.reason(), StopReason::Cancelled);
+/// assert_eq!(err.attempts(), 1);
+/// assert_eq!(*err.last(), "unavailable");
+/// assert!(started.elapsed() < Duration::from_secs(3600));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: bool,
+
+    /// The tasks waiting in [`CancelWaiter::poll_cancelled`], keyed by waiter.
+    wakers: Vec<(usize, Waker)>,
+
+    /// The key given to the next [`CancelWaiter`].
+    #[cfg(feature = "async-core")]
+    next_key: usize,
+}
+
+impl CancelToken {
+    /// Constructs a new token which hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels every [`Attempt`](crate::Attempt) using this token, waking them up if they are
+    /// sleeping.
+    pub fn cancel(&self) {
+        let mut state = self.state();
+        state.cancelled = true;
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+
+        self.inner.condvar.notify_all();
+        for (_, waker) in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether [`CancelToken::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.state().cancelled
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Blocks the current thread for `duration`, or until the token is cancelled.
+    ///
+    /// Returns `true` if the full duration was slept, or `false` if the token was cancelled.
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now().checked_add(duration);
+        let mut state = self.state();
+
+        while !state.cancelled {
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) if !timeout.is_zero() => timeout,
+                    _ => return true,
+                },
+                None => Duration::MAX,
+            };
+
+            state = self
+                .inner
+                .condvar
+                .wait_timeout(state, timeout)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+
+        false
+    }
+
+    /// Returns a [`CancelWaiter`] used by a task to wait for this token to be cancelled.
+    #[cfg(feature = "async-core")]
+    pub(crate) fn waiter(&self) -> CancelWaiter {
+        CancelWaiter {
+            token: self.clone(),
+            key: None,
+        }
+    }
+}
+
+/// The registration of a task waiting for a [`CancelToken`] to be cancelled, removed from the
+/// token when dropped so that waits which end normally don't leave their waker behind.
+#[cfg(feature = "async-core")]
+#[derive(Debug)]
+pub(crate) struct CancelWaiter {
+    token: CancelToken,
+
+    /// The key of the waker registered in the token, if any.
+    key: Option<usize>,
+}
+
+#[cfg(feature = "async-core")]
+impl CancelWaiter {
+    /// Resolves once the token is cancelled, registering the task to be woken up otherwise.
+    pub(crate) fn poll_cancelled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.token.state();
+        if state.cancelled {
+            return Poll::Ready(());
+        }
+
+        let registered = self
+            .key
+            .and_then(|key| state.wakers.iter_mut().find(|(other, _)| *other == key));
+        match registered {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            None => {
+                let key = state.next_key;
+                state.next_key += 1;
+                state.wakers.push((key, cx.waker().clone()));
+                self.key = Some(key);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "async-core")]
+impl Drop for CancelWaiter {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.state().wakers.retain(|(other, _)| *other != key);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Circuit breaking, to stop calling a dependency which keeps failing.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{AttemptHooks, RetryError, StopReason};
+
+/// The default share of failed calls which opens a [`CircuitBreaker`].
+const DEFAULT_FAILURE_RATE: f32 = 0.5;
+
+/// The default number of calls the failure rate of a [`CircuitBreaker`] is computed over.
+const DEFAULT_WINDOW: usize = 20;
+
+/// The default time a [`CircuitBreaker`] stays open before letting a trial call through.
+const DEFAULT_COOL_DOWN: Duration = Duration::from_secs(30);
+
+/// Tracks the outcome of the calls made by many [`Attempt`](crate::Attempt)s to the same
+/// dependency, and makes them fail fast once it looks down.
+///
+/// The breaker starts out [closed](CircuitState::Closed), letting every call through. Once the
+/// share of failures among the last [`CircuitBreaker::window`] calls reaches
+/// [`CircuitBreaker::failure_rate`], it [opens](CircuitState::Open): any [`Attempt`](crate::Attempt)
+/// using it then gives up right away with [`CircuitOpen`] instead of calling the function. After
+/// [`CircuitBreaker::cool_down`], the breaker is [half-open](CircuitState::HalfOpen) and lets a
+/// single trial call through, which closes it again on success or reopens it on failure.
+///
+/// Cloning the breaker yields another handle to the same state, so a single breaker can be
+/// shared by every call site (and thread) talking to a dependency. It is attached to an
+/// [`Attempt`](crate::Attempt) with [`Attempt::circuit_breaker`](crate::Attempt::circuit_breaker).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, CircuitBreaker, CircuitOpen, CircuitState, StopReason};
+/// # use std::cell::Cell;
+/// # use std::time::Duration;
+/// let breaker = CircuitBreaker::new().window(2).cool_down(Duration::from_secs(60));
+///
+/// let calls = Cell::new(0);
+/// let call_dependency = || {
+///     calls.set(calls.get() + 1);
+///     Err::<(), _>(String::from("connection refused"))
+/// };
+///
+/// // The first two failures open the circuit, so the third call is never made.
+/// let res = Attempt::to(&call_dependency)
+///     .no_delay()
+///     .max_tries(3)
+///     .circuit_breaker(breaker.clone())
+///     .run();
+///
+/// assert_eq!(res, Err(CircuitOpen.to_string()));
+/// assert_eq!(calls.get(), 2);
+/// assert_eq!(breaker.state(), CircuitState::Open);
+///
+/// // Other runs fail fast while the circuit is open.
+/// let err = Attempt::to(&call_dependency)
+///     .circuit_breaker(breaker)
+///     .run_detailed()
+///     .unwrap_err();
+///
+/// assert_eq!(err.reason(), StopReason::Rejected);
+/// assert_eq!(err.attempts(), 0);
+/// assert_eq!(calls.get(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    inner: Arc<Mutex<State>>,
+}
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls are let through, and their outcome is recorded.
+    Closed,
+
+    /// Too many calls failed recently, so new calls are rejected until the cool-down is over.
+    Open,
+
+    /// The cool-down is over and a trial call decides whether the circuit closes or reopens.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct State {
+    failure_rate: f32,
+    window: usize,
+    cool_down: Duration,
+    circuit: Circuit,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            failure_rate: DEFAULT_FAILURE_RATE,
+            window: DEFAULT_WINDOW,
+            cool_down: DEFAULT_COOL_DOWN,
+            circuit: Circuit::Closed {
+                outcomes: VecDeque::new(),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Circuit {
+    /// `outcomes` holds whether each of the most recent calls failed, oldest first.
+    Closed {
+        outcomes: VecDeque<bool>,
+    },
+    Open {
+        since: Instant,
+    },
+
+    /// `probe` is when the trial call was let through. Should its outcome never be recorded
+    /// (e.g. because the future making it was dropped), another one is let through after the
+    /// cool-down.
+    HalfOpen {
+        probe: Instant,
+    },
+}
+
+impl CircuitBreaker {
+    /// Constructs a new, closed breaker which opens once half of the last 20 calls failed, and
+    /// cools down for 30 seconds.
+    pub fn new() -> CircuitBreaker {
+        CircuitBreaker::default()
+    }
+
+    /// Sets the share of failed calls, between 0 (exclusive) and 1 (inclusive), at which the
+    /// circuit opens. Checked by assertion.
+    pub fn failure_rate(self, failure_rate: f32) -> Self {
+        assert!(failure_rate > 0.0 && failure_rate <= 1.0);
+        self.lock().failure_rate = failure_rate;
+
+        self
+    }
+
+    /// Sets the number of most recent calls the failure rate is computed over. The circuit
+    /// can't open before that many calls were made. Must be greater than 0 (checked by
+    /// assertion).
+    pub fn window(self, calls: usize) -> Self {
+        assert!(calls > 0);
+        self.lock().window = calls;
+
+        self
+    }
+
+    /// Sets how long the circuit stays open before letting a trial call through.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, CircuitBreaker, CircuitState};
+    /// # use std::time::Duration;
+    /// let breaker = CircuitBreaker::new()
+    ///     .window(1)
+    ///     .cool_down(Duration::from_millis(10));
+    ///
+    /// let _ = Attempt::to(|| Err::<(), _>(String::from("down")))
+    ///     .max_tries(1)
+    ///     .circuit_breaker(breaker.clone())
+    ///     .run();
+    /// assert_eq!(breaker.state(), CircuitState::Open);
+    ///
+    /// std::thread::sleep(Duration::from_millis(10));
+    /// let res = Attempt::to(|| Ok::<_, String>("up again"))
+    ///     .circuit_breaker(breaker.clone())
+    ///     .run();
+    ///
+    /// assert_eq!(res.as_deref(), Ok("up again"));
+    /// assert_eq!(breaker.state(), CircuitState::Closed);
+    /// ```
+    pub fn cool_down(self, cool_down: Duration) -> Self {
+        self.lock().cool_down = cool_down;
+
+        self
+    }
+
+    /// Returns the current state of the circuit.
+    ///
+    /// An open circuit is only reported as half-open once a call was let through after the
+    /// cool-down.
+    pub fn state(&self) -> CircuitState {
+        match self.lock().circuit {
+            Circuit::Closed { .. } => CircuitState::Closed,
+            Circuit::Open { .. } => CircuitState::Open,
+            Circuit::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns whether a call can be made right now, moving an open circuit to half-open once
+    /// the cool-down is over.
+    pub(crate) fn try_acquire(&self) -> bool {
+        let mut state = self.lock();
+        let cool_down = state.cool_down;
+
+        match state.circuit {
+            Circuit::Closed { .. } => true,
+            Circuit::Open { since: started } | Circuit::HalfOpen { probe: started } => {
+                if started.elapsed() < cool_down {
+                    return false;
+                }
+
+                state.circuit = Circuit::HalfOpen {
+                    probe: Instant::now(),
+                };
+
+                true
+            }
+        }
+    }
+
+    /// Records the outcome of a call let through by [`CircuitBreaker::try_acquire`].
+    pub(crate) fn record(&self, failed: bool) {
+        let mut state = self.lock();
+        let (failure_rate, window) = (state.failure_rate, state.window);
+
+        match &mut state.circuit {
+            Circuit::Closed { outcomes } => {
+                outcomes.push_back(failed);
+                while outcomes.len() > window {
+                    outcomes.pop_front();
+                }
+
+                let failures = outcomes.iter().filter(|&&failed| failed).count();
+                if outcomes.len() == window && failures as f32 >= failure_rate * window as f32 {
+                    state.circuit = Circuit::Open {
+                        since: Instant::now(),
+                    };
+                }
+            }
+            Circuit::HalfOpen { .. } if failed => {
+                state.circuit = Circuit::Open {
+                    since: Instant::now(),
+                };
+            }
+            Circuit::HalfOpen { .. } => {
+                state.circuit = Circuit::Closed {
+                    outcomes: VecDeque::new(),
+                };
+            }
+            // The call was let through before the circuit opened.
+            Circuit::Open { .. } => {}
+        }
+    }
+}
+
+/// The error an [`Attempt`](crate::Attempt) gives up with when its [`CircuitBreaker`] is open.
+///
+/// The error type of a function guarded by a breaker must be convertible from this one, which is
+/// already the case for [`String`], [`std::io::Error`] and boxed errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("circuit breaker is open")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+impl From<CircuitOpen> for String {
+    fn from(err: CircuitOpen) -> Self {
+        err.to_string()
+    }
+}
+
+impl From<CircuitOpen> for io::Error {
+    fn from(err: CircuitOpen) -> Self {
+        io::Error::other(err)
+    }
+}
+
+/// [`AttemptHooks`] which report the outcome of each call to a [`CircuitBreaker`] before
+/// delegating to other hooks, and reject calls while it is open.
+///
+/// Errors classified as not worth retrying by [`Attempt::retry_if`](crate::Attempt::retry_if)
+/// aren't recorded at all: they usually mean the dependency refused the request, which says
+/// nothing about whether it is healthy. A trial call failing with one leaves the circuit
+/// half-open, so another trial call is let through after the cool-down.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, CircuitBreaker, CircuitState};
+/// # use std::time::Duration;
+/// let breaker = CircuitBreaker::new().window(1).cool_down(Duration::ZERO);
+///
+/// let _ = Attempt::to(|| Err::<(), _>(String::from("unavailable")))
+///     .max_tries(1)
+///     .circuit_breaker(breaker.clone())
+///     .run();
+/// assert_eq!(breaker.state(), CircuitState::Open);
+///
+/// let _ = Attempt::to(|| Err::<(), _>(String::from("not found")))
+///     .retry_if(|err: &String| err != "not found")
+///     .circuit_breaker(breaker.clone())
+///     .run();
+/// assert_eq!(breaker.state(), CircuitState::HalfOpen);
+/// ```
+///
+/// See [`Attempt::circuit_breaker`](crate::Attempt::circuit_breaker).
+#[derive(Debug, Clone)]
+pub struct WithCircuitBreaker<H> {
+    hooks: H,
+    breaker: CircuitBreaker,
+
+    /// The number of calls whose outcome was recorded, so that failures reported by both
+    /// `on_retry` and `on_give_up` are only counted once.
+    recorded: usize,
+}
+
+impl<H> WithCircuitBreaker<H> {
+    pub(crate) fn new(hooks: H, breaker: CircuitBreaker) -> WithCircuitBreaker<H> {
+        WithCircuitBreaker {
+            hooks,
+            breaker,
+            recorded: 0,
+        }
+    }
+}
+
+impl<E, H> AttemptHooks<E> for WithCircuitBreaker<H>
+where
+    E: From<CircuitOpen>,
+    H: AttemptHooks<E>,
+{
+    fn before_attempt(&mut self, attempt: usize) -> Result<(), E> {
+        self.hooks.before_attempt(attempt)?;
+
+        if self.breaker.try_acquire() {
+            Ok(())
+        } else {
+            Err(CircuitOpen.into())
+        }
+    }
+
+    fn on_retry(&mut self, err: &E, attempt: usize, delay: Duration) {
+        self.breaker.record(true);
+        self.recorded = attempt;
+        self.hooks.on_retry(err, attempt, delay);
+    }
+
+    fn on_success(&mut self, attempts: usize) {
+        self.breaker.record(false);
+        self.recorded = attempts;
+        self.hooks.on_success(attempts);
+    }
+
+    fn on_give_up(&mut self, err: &RetryError<E>) {
+        if err.attempts() > self.recorded {
+            if err.reason() != StopReason::NonRetryable {
+                self.breaker.record(true);
+            }
+            self.recorded = err.attempts();
+        }
+
+        self.hooks.on_give_up(err);
+    }
+}
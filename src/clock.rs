@@ -0,0 +1,114 @@
+//! Pluggable time for [`Attempt::run`](crate::Attempt::run), so retries can be tested without
+//! actually sleeping.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The source of time used by an [`Attempt`](crate::Attempt) to measure elapsed time and, for
+/// [`Attempt::run`](crate::Attempt::run), to sleep between attempts.
+///
+/// By default, the system clock is used with [`std::thread::sleep`]. Another clock can be set
+/// with [`Attempt::clock`](crate::Attempt::clock), most usefully a [`MockClock`] in tests.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the current thread until `duration` has passed.
+    fn sleep(&self, duration: Duration);
+}
+
+/// A [`Clock`] whose time only moves when it is told to, which records every sleep instead of
+/// blocking.
+///
+/// Sleeping advances the virtual time instantly, so an [`Attempt`](crate::Attempt) with long
+/// delays runs in no time, and [`MockClock::sleeps`] gives the exact schedule it followed.
+/// Deadlines are checked against the virtual time too, and [`MockClock::advance`] simulates
+/// calls which take a while.
+///
+/// Cloning the clock yields another handle to the same virtual time, so a clone can be given to
+/// the [`Attempt`](crate::Attempt) while the original is used to inspect it.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, MockClock, StopReason};
+/// # use std::time::Duration;
+/// let clock = MockClock::new();
+///
+/// let err = Attempt::to(|| Err::<(), _>("unavailable"))
+///     .delay(Duration::from_secs(1))
+///     .delay_growth_magnitude(2.0)
+///     .deadline(Duration::from_secs(10))
+///     .no_max_tries()
+///     .clock(clock.clone())
+///     .run_detailed()
+///     .unwrap_err();
+///
+/// assert_eq!(err.reason(), StopReason::Deadline);
+/// assert_eq!(clock.sleeps(), [1, 2, 4].map(Duration::from_secs));
+/// assert_eq!(clock.elapsed(), Duration::from_secs(7));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    inner: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    elapsed: Duration,
+    sleeps: Vec<Duration>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock {
+            inner: Arc::new(Mutex::new(State {
+                started: Instant::now(),
+                elapsed: Duration::ZERO,
+                sleeps: Vec::new(),
+            })),
+        }
+    }
+}
+
+impl MockClock {
+    /// Constructs a new clock, starting at the current time.
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+
+    /// Moves the virtual time forward by `duration`, without recording a sleep.
+    pub fn advance(&self, duration: Duration) {
+        self.lock().elapsed += duration;
+    }
+
+    /// Returns the virtual time which passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    /// Returns the duration of every sleep so far, in order, including sleeps of zero.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.lock().sleeps.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        let state = self.lock();
+
+        state.started + state.elapsed
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let mut state = self.lock();
+        state.elapsed += duration;
+        state.sleeps.push(duration);
+    }
+}
@@ -0,0 +1,142 @@
+//! Information about the retry loop made available to the function being retried.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Describes the attempt about to be made, passed to functions given to
+/// [`Attempt::with_context`](crate::Attempt::with_context).
+///
+/// This lets the function change its behavior on retries, e.g. by switching to a fallback
+/// endpoint, raising a timeout or adding an idempotency key.
+///
+/// # Example
+/// ```rust
+/// # use attempt::Attempt;
+/// # use std::time::Duration;
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let res = Attempt::with_context(|cx| {
+///     // Anything borrowed from the context must be extracted before the future is created.
+///     let (attempt, previous_delay) = (cx.attempt(), cx.previous_delay());
+///
+///     async move {
+///         if attempt < 3 { Err(attempt) } else { Ok(previous_delay) }
+///     }
+/// })
+/// .delay(Duration::from_millis(1))
+/// .delay_growth_magnitude(2.0)
+/// # .sleeper(tokio::time::sleep)
+/// .run_async()
+/// .await;
+///
+/// assert_eq!(res, Ok(Some(Duration::from_millis(2))));
+/// # });
+/// ```
+pub struct AttemptContext<'a, E> {
+    pub(crate) attempt: usize,
+    pub(crate) elapsed: Duration,
+    pub(crate) previous_delay: Option<Duration>,
+    pub(crate) previous_error: Option<&'a E>,
+
+    /// The sleeper of [`Attempt::run_async`](crate::Attempt::run_async), used for timeouts.
+    /// [`None`] for synchronous runs.
+    #[cfg(feature = "async-core")]
+    pub(crate) sleeper: Option<&'a dyn crate::Sleeper>,
+}
+
+impl<E: fmt::Debug> fmt::Debug for AttemptContext<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttemptContext")
+            .field("attempt", &self.attempt)
+            .field("elapsed", &self.elapsed)
+            .field("previous_delay", &self.previous_delay)
+            .field("previous_error", &self.previous_error)
+            .finish()
+    }
+}
+
+impl<'a, E> AttemptContext<'a, E> {
+    /// Returns the number of this attempt, starting at 1 for the first call.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Returns whether this is a retry, i.e. not the first call.
+    pub fn is_retry(&self) -> bool {
+        self.attempt > 1
+    }
+
+    /// Returns the time since the first call was started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the delay slept for right before this attempt, or [`None`] on the first call.
+    pub fn previous_delay(&self) -> Option<Duration> {
+        self.previous_delay
+    }
+
+    /// Returns the error of the previous call, or [`None`] on the first call.
+    pub fn previous_error(&self) -> Option<&E> {
+        self.previous_error
+    }
+
+    /// Returns a context identical to this one, except for the previous error which is mapped
+    /// with `f`.
+    pub(crate) fn map_error<D>(
+        &self,
+        f: impl FnOnce(&'a E) -> Option<&'a D>,
+    ) -> AttemptContext<'a, D> {
+        AttemptContext {
+            attempt: self.attempt,
+            elapsed: self.elapsed,
+            previous_delay: self.previous_delay,
+            previous_error: self.previous_error.and_then(f),
+            #[cfg(feature = "async-core")]
+            sleeper: self.sleeper,
+        }
+    }
+}
+
+/// A function which can be retried by an [`Attempt`](crate::Attempt).
+///
+/// This is implemented for closures taking no arguments, as accepted by
+/// [`Attempt::to`](crate::Attempt::to), and for closures taking an [`AttemptContext`] wrapped in
+/// [`WithContext`], as created by [`Attempt::with_context`](crate::Attempt::with_context).
+/// `Output` is either a [`Result`] or, for [`Attempt::run_async`](crate::Attempt::run_async), a
+/// future resolving to one.
+pub trait Operation<E> {
+    /// The value produced by a single call.
+    type Output;
+
+    /// Calls the function once.
+    fn call(&mut self, cx: &AttemptContext<'_, E>) -> Self::Output;
+}
+
+impl<E, F, R> Operation<E> for F
+where
+    F: FnMut() -> R,
+{
+    type Output = R;
+
+    fn call(&mut self, _cx: &AttemptContext<'_, E>) -> R {
+        self()
+    }
+}
+
+/// Wraps a function which takes an [`AttemptContext`] so it can be used as an [`Operation`].
+///
+/// See [`Attempt::with_context`](crate::Attempt::with_context).
+#[derive(Debug, Clone, Copy)]
+pub struct WithContext<F>(pub F);
+
+impl<E, F, R> Operation<E> for WithContext<F>
+where
+    F: FnMut(&AttemptContext<'_, E>) -> R,
+{
+    type Output = R;
+
+    fn call(&mut self, cx: &AttemptContext<'_, E>) -> R {
+        (self.0)(cx)
+    }
+}
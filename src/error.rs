@@ -0,0 +1,188 @@
+//! The detailed error returned when an [`Attempt`](crate::Attempt) gives up.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+/// The reason an [`Attempt`](crate::Attempt) stopped retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StopReason {
+    /// The configured maximum number of tries was reached.
+    MaxTries,
+
+    /// Sleeping before the next attempt would have overshot the configured deadline.
+    Deadline,
+
+    /// The retry predicate classified the last error as not worth retrying.
+    NonRetryable,
+
+    /// The [`Backoff`](crate::Backoff) returned [`None`] instead of a delay.
+    BackoffExhausted,
+
+    /// The [`CancelToken`](crate::CancelToken) of the [`Attempt`](crate::Attempt) was
+    /// cancelled.
+    Cancelled,
+
+    /// The next call was rejected by the hooks of the [`Attempt`](crate::Attempt), e.g. because
+    /// its [`CircuitBreaker`](crate::CircuitBreaker) is open. The error they returned instead is
+    /// the last one.
+    Rejected,
+
+    /// The [`RetryBudget`](crate::RetryBudget) of the [`Attempt`](crate::Attempt) had no retries
+    /// left.
+    BudgetExhausted,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopReason::MaxTries => "maximum tries reached",
+            StopReason::Deadline => "deadline exceeded",
+            StopReason::NonRetryable => "error is not retryable",
+            StopReason::BackoffExhausted => "backoff exhausted",
+            StopReason::Cancelled => "cancelled",
+            StopReason::Rejected => "call rejected",
+            StopReason::BudgetExhausted => "retry budget exhausted",
+        })
+    }
+}
+
+/// The error returned by [`Attempt::run_detailed`](crate::Attempt::run_detailed) and
+/// [`Attempt::run_async_detailed`](crate::Attempt::run_async_detailed).
+///
+/// Unlike [`Attempt::run`](crate::Attempt::run), which only returns the final error, this type
+/// keeps the errors of every failed call (or of the last few, see
+/// [`Attempt::error_history`](crate::Attempt::error_history)) along with some bookkeeping about
+/// the run. It always holds at least one error.
+///
+/// When `E` implements [`std::error::Error`], so does [`RetryError`], with the final error as
+/// its [`source`](std::error::Error::source).
+///
+/// # Example
+/// ```rust
+/// # use attempt::Attempt;
+/// # use std::error::Error;
+/// # use std::io;
+/// let err = Attempt::to(|| Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no")))
+///     .max_tries(2)
+///     .run_detailed()
+///     .unwrap_err();
+///
+/// assert_eq!(err.source().unwrap().to_string(), "oh no");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryError<E> {
+    errors: VecDeque<E>,
+    attempts: usize,
+    elapsed: Duration,
+    reason: StopReason,
+}
+
+impl<E> RetryError<E> {
+    /// Returns the collected errors, oldest first.
+    pub fn errors(&self) -> impl ExactSizeIterator<Item = &E> + DoubleEndedIterator {
+        self.errors.iter()
+    }
+
+    /// Consumes this error, returning the collected errors, oldest first.
+    pub fn into_errors(self) -> Vec<E> {
+        self.errors.into()
+    }
+
+    /// Returns the error of the final call to the function.
+    pub fn last(&self) -> &E {
+        self.errors
+            .back()
+            .expect("RetryError always holds an error")
+    }
+
+    /// Consumes this error, returning the error of the final call to the function.
+    pub fn into_last(mut self) -> E {
+        self.errors
+            .pop_back()
+            .expect("RetryError always holds an error")
+    }
+
+    /// Returns the number of calls made to the function.
+    ///
+    /// This can be larger than the number of collected errors when the history is bounded, and
+    /// is one less than it when the last call was [rejected](StopReason::Rejected).
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Returns the time between the start of the first call and giving up.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns why the [`Attempt`](crate::Attempt) stopped retrying.
+    pub fn reason(&self) -> StopReason {
+        self.reason
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gave up after {} attempt{} in {:?} ({}): {}",
+            self.attempts,
+            if self.attempts == 1 { "" } else { "s" },
+            self.elapsed,
+            self.reason,
+            self.last(),
+        )
+    }
+}
+
+impl<E> std::error::Error for RetryError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.last())
+    }
+}
+
+/// Collects the errors of a run, keeping at most `limit` of the most recent ones.
+pub(crate) struct ErrorHistory<E> {
+    errors: VecDeque<E>,
+    limit: Option<usize>,
+}
+
+impl<E> ErrorHistory<E> {
+    pub(crate) fn new(limit: Option<usize>) -> ErrorHistory<E> {
+        ErrorHistory {
+            errors: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub(crate) fn push(&mut self, err: E) {
+        if self.limit == Some(self.errors.len()) {
+            self.errors.pop_front();
+        }
+
+        self.errors.push_back(err);
+    }
+
+    pub(crate) fn last(&self) -> Option<&E> {
+        self.errors.back()
+    }
+
+    pub(crate) fn finish(
+        self,
+        attempts: usize,
+        elapsed: Duration,
+        reason: StopReason,
+    ) -> RetryError<E> {
+        RetryError {
+            errors: self.errors,
+            attempts,
+            elapsed,
+            reason,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Chains of alternative operations, tried in order once the previous one gave up.
+
+#[cfg(feature = "async-core")]
+use std::future::Future;
+
+#[cfg(feature = "async-core")]
+use crate::Sleeper;
+use crate::{Attempt, AttemptHooks, Operation, RetryAfterMode, RetryPredicate};
+
+/// An [`Attempt`] followed by fallbacks, each tried with its own retry settings once the
+/// previous stage gave up, e.g. to fall back from a primary service to a secondary one and then
+/// to a cache.
+///
+/// A fallback is a closure which receives the error the previous stage gave up with and returns
+/// the [`Attempt`] to run next. The value is returned as a [`Staged`], which tells which stage
+/// produced it. When every stage gives up, the error of the last one is returned.
+///
+/// See [`Attempt::fallback`].
+///
+/// # Example
+/// ```rust
+/// # use attempt::Attempt;
+/// # use std::time::Duration;
+/// let mut primary_calls = 0;
+/// let res = Attempt::to(|| {
+///     primary_calls += 1;
+///     Err::<&str, _>("primary is down")
+/// })
+/// .max_tries(3)
+/// .fallback(|err: &&str| {
+///     assert_eq!(*err, "primary is down");
+///     Attempt::to(|| Err("secondary is down")).max_tries(2)
+/// })
+/// .fallback(|_: &&str| Attempt::to(|| Ok("cached value")).no_delay())
+/// .run()
+/// .unwrap();
+///
+/// assert_eq!(primary_calls, 3);
+/// assert_eq!(res.stage(), 2);
+/// assert_eq!(res.into_value(), "cached value");
+/// ```
+#[derive(Debug)]
+pub struct Fallback<A, G> {
+    first: A,
+    fallback: G,
+
+    /// The index of the stage run by `fallback`.
+    stage: usize,
+}
+
+impl<A, G> Fallback<A, G> {
+    pub(crate) fn new(first: A, fallback: G, stage: usize) -> Fallback<A, G> {
+        Fallback {
+            first,
+            fallback,
+            stage,
+        }
+    }
+
+    /// Adds another fallback, tried once every previous stage gave up. See
+    /// [`Attempt::fallback`].
+    pub fn fallback<G2>(self, fallback: G2) -> Fallback<Self, G2> {
+        let stage = self.stage + 1;
+
+        Fallback::new(self, fallback, stage)
+    }
+
+    /// Runs every stage in order until one of them succeeds, like [`Attempt::run`].
+    pub fn run<T, E>(self) -> Result<Staged<T>, E>
+    where
+        Self: RunStages<T, E>,
+    {
+        self.run_stages()
+    }
+
+    /// Runs every stage in order until one of them succeeds, like [`Attempt::run_async`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let res = Attempt::to(|| async { Err::<u32, _>("primary is down") })
+    ///     .max_tries(2)
+    /// #   .sleeper(tokio::time::sleep)
+    ///     .fallback(|_: &&str| {
+    ///         Attempt::to(|| async { Ok(42) })
+    /// #           .sleeper(tokio::time::sleep)
+    ///     })
+    ///     .run_async()
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_eq!((res.stage(), *res.value()), (1, 42));
+    /// # fn assert_send(_: impl Send) {}
+    /// # assert_send(
+    /// #     Attempt::to(|| async { Err::<(), _>(()) })
+    /// #         .sleeper(tokio::time::sleep)
+    /// #         .fallback(|_: &()| Attempt::to(|| async { Ok(()) }).sleeper(tokio::time::sleep))
+    /// #         .run_async(),
+    /// # );
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_async<T, E>(self) -> Result<Staged<T>, E>
+    where
+        Self: RunStagesAsync<T, E>,
+    {
+        self.run_stages_async().await
+    }
+}
+
+/// A value returned by a [`Fallback`] chain, along with the stage which produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Staged<T> {
+    value: T,
+    stage: usize,
+}
+
+impl<T> Staged<T> {
+    /// Returns the index of the stage which produced the value: 0 for the [`Attempt`] the chain
+    /// started with, 1 for the first fallback, and so on.
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+
+    /// Returns whether the value was produced by a fallback rather than by the [`Attempt`] the
+    /// chain started with.
+    pub fn is_fallback(&self) -> bool {
+        self.stage > 0
+    }
+
+    /// Returns the value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Consumes this, returning the value.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+/// The stages of a synchronous [`Fallback`] chain.
+///
+/// This trait is implemented for [`Attempt`] and [`Fallback`], and can't be implemented
+/// outside of this crate.
+pub trait RunStages<T, E> {
+    /// Runs every stage in order until one of them succeeds.
+    fn run_stages(self) -> Result<Staged<T>, E>;
+}
+
+impl<F, P, H, Z, A, T, E> RunStages<T, E> for Attempt<F, P, H, Z, A>
+where
+    F: Operation<E, Output = Result<T, E>>,
+    P: RetryPredicate<E>,
+    A: RetryAfterMode<E>,
+    H: AttemptHooks<E>,
+{
+    fn run_stages(self) -> Result<Staged<T>, E> {
+        self.run().map(|value| Staged { value, stage: 0 })
+    }
+}
+
+impl<A, G, B, T, E> RunStages<T, E> for Fallback<A, G>
+where
+    A: RunStages<T, E>,
+    G: FnOnce(&E) -> B,
+    B: RunStages<T, E>,
+{
+    fn run_stages(self) -> Result<Staged<T>, E> {
+        match self.first.run_stages() {
+            Ok(staged) => Ok(staged),
+            Err(err) => (self.fallback)(&err).run_stages().map(|staged| Staged {
+                value: staged.value,
+                stage: self.stage,
+            }),
+        }
+    }
+}
+
+/// The stages of an asynchronous [`Fallback`] chain.
+///
+/// This trait is implemented for [`Attempt`] and [`Fallback`], and can't be implemented
+/// outside of this crate.
+#[cfg(feature = "async-core")]
+pub trait RunStagesAsync<T, E> {
+    /// Runs every stage in order until one of them succeeds.
+    fn run_stages_async(self) -> impl Future<Output = Result<Staged<T>, E>>;
+}
+
+#[cfg(feature = "async-core")]
+impl<F, P, H, Z, A, Fut, T, E> RunStagesAsync<T, E> for Attempt<F, P, H, Z, A>
+where
+    F: Operation<E, Output = Fut>,
+    Fut: Future<Output = Result<T, E>>,
+    P: RetryPredicate<E>,
+    A: RetryAfterMode<E>,
+    H: AttemptHooks<E>,
+    Z: Sleeper + Send + Sync,
+{
+    async fn run_stages_async(self) -> Result<Staged<T>, E> {
+        self.run_async()
+            .await
+            .map(|value| Staged { value, stage: 0 })
+    }
+}
+
+#[cfg(feature = "async-core")]
+impl<A, G, B, T, E> RunStagesAsync<T, E> for Fallback<A, G>
+where
+    A: RunStagesAsync<T, E>,
+    G: FnOnce(&E) -> B,
+    B: RunStagesAsync<T, E>,
+{
+    async fn run_stages_async(self) -> Result<Staged<T>, E> {
+        match self.first.run_stages_async().await {
+            Ok(staged) => Ok(staged),
+            Err(err) => {
+                let staged = (self.fallback)(&err).run_stages_async().await?;
+
+                Ok(Staged {
+                    value: staged.value,
+                    stage: self.stage,
+                })
+            }
+        }
+    }
+}
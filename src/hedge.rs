@@ -0,0 +1,44 @@
+//! The concurrent calls of [`Attempt::run_hedged`](crate::Attempt::run_hedged).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The calls to the function which were started and didn't complete yet, each with a `K`
+/// identifying it.
+pub(crate) struct InFlight<K, Fut> {
+    calls: Vec<(K, Pin<Box<Fut>>)>,
+}
+
+impl<K, Fut: Future> InFlight<K, Fut> {
+    pub(crate) fn new() -> InFlight<K, Fut> {
+        InFlight { calls: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, key: K, call: Fut) {
+        self.calls.push((key, Box::pin(call)));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Polls every call, returning the output of the first one to complete along with its key.
+    ///
+    /// Returns [`Poll::Pending`] when there are no calls.
+    pub(crate) fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<(K, Fut::Output)> {
+        for i in 0..self.calls.len() {
+            if let Poll::Ready(output) = self.calls[i].1.as_mut().poll(cx) {
+                let (key, _) = self.calls.swap_remove(i);
+
+                return Poll::Ready((key, output));
+            }
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,133 @@
+//! Callbacks invoked as an [`Attempt`](crate::Attempt) runs.
+
+use std::time::Duration;
+
+use crate::RetryError;
+
+/// Observes the progress of an [`Attempt`](crate::Attempt), e.g. for logging or counting
+/// retries.
+///
+/// Every method has an empty default implementation, so implementors only need to override the
+/// events they care about. The [`Hooks`] type implements this trait by dispatching to the
+/// closures given to [`Attempt::on_retry`](crate::Attempt::on_retry),
+/// [`Attempt::on_success`](crate::Attempt::on_success) and
+/// [`Attempt::on_give_up`](crate::Attempt::on_give_up).
+pub trait AttemptHooks<E> {
+    /// Called right before the `attempt`th call to the function. Returning an error gives up
+    /// with it instead of making the call, reporting
+    /// [`StopReason::Rejected`](crate::StopReason::Rejected).
+    ///
+    /// This is how a [`CircuitBreaker`](crate::CircuitBreaker) makes runs fail fast while it is
+    /// open.
+    fn before_attempt(&mut self, attempt: usize) -> Result<(), E> {
+        let _ = attempt;
+        Ok(())
+    }
+
+    /// Called after the `attempt`th call failed with `err`, right before sleeping for `delay`
+    /// and trying again.
+    fn on_retry(&mut self, err: &E, attempt: usize, delay: Duration) {
+        let _ = (err, attempt, delay);
+    }
+
+    /// Called once the function returned [`Ok`] on the `attempts`th call.
+    fn on_success(&mut self, attempts: usize) {
+        let _ = attempts;
+    }
+
+    /// Called once the [`Attempt`](crate::Attempt) stops retrying and is about to return an
+    /// error.
+    ///
+    /// When running with [`Attempt::run`](crate::Attempt::run) or
+    /// [`Attempt::run_async`](crate::Attempt::run_async), only the final error is kept in `err`.
+    fn on_give_up(&mut self, err: &RetryError<E>) {
+        let _ = err;
+    }
+}
+
+/// A closure (or `()` for none) called before each retry. See
+/// [`Attempt::on_retry`](crate::Attempt::on_retry).
+pub trait RetryHook<E> {
+    /// Invokes the hook.
+    fn call(&mut self, err: &E, attempt: usize, delay: Duration);
+}
+
+impl<E> RetryHook<E> for () {
+    fn call(&mut self, _err: &E, _attempt: usize, _delay: Duration) {}
+}
+
+impl<E, F> RetryHook<E> for F
+where
+    F: FnMut(&E, usize, Duration),
+{
+    fn call(&mut self, err: &E, attempt: usize, delay: Duration) {
+        self(err, attempt, delay)
+    }
+}
+
+/// A closure (or `()` for none) called on success. See
+/// [`Attempt::on_success`](crate::Attempt::on_success).
+pub trait SuccessHook {
+    /// Invokes the hook.
+    fn call(&mut self, attempts: usize);
+}
+
+impl SuccessHook for () {
+    fn call(&mut self, _attempts: usize) {}
+}
+
+impl<F> SuccessHook for F
+where
+    F: FnMut(usize),
+{
+    fn call(&mut self, attempts: usize) {
+        self(attempts)
+    }
+}
+
+/// A closure (or `()` for none) called on final failure. See
+/// [`Attempt::on_give_up`](crate::Attempt::on_give_up).
+pub trait GiveUpHook<E> {
+    /// Invokes the hook.
+    fn call(&mut self, err: &RetryError<E>);
+}
+
+impl<E> GiveUpHook<E> for () {
+    fn call(&mut self, _err: &RetryError<E>) {}
+}
+
+impl<E, F> GiveUpHook<E> for F
+where
+    F: FnMut(&RetryError<E>),
+{
+    fn call(&mut self, err: &RetryError<E>) {
+        self(err)
+    }
+}
+
+/// The default [`AttemptHooks`], made up of one optional closure per event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hooks<R = (), S = (), G = ()> {
+    pub(crate) on_retry: R,
+    pub(crate) on_success: S,
+    pub(crate) on_give_up: G,
+}
+
+impl<E, R, S, G> AttemptHooks<E> for Hooks<R, S, G>
+where
+    R: RetryHook<E>,
+    S: SuccessHook,
+    G: GiveUpHook<E>,
+{
+    fn on_retry(&mut self, err: &E, attempt: usize, delay: Duration) {
+        self.on_retry.call(err, attempt, delay);
+    }
+
+    fn on_success(&mut self, attempts: usize) {
+        self.on_success.call(attempts);
+    }
+
+    fn on_give_up(&mut self, err: &RetryError<E>) {
+        self.on_give_up.call(err);
+    }
+}
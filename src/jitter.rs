@@ -0,0 +1,152 @@
+//! Randomization of the delays between attempts.
+
+use std::time::Duration;
+
+use crate::backoff::{decorrelated, random_between, Backoff};
+
+/// How the delays produced by a [`Backoff`] are randomized before sleeping.
+///
+/// Without jitter, every client configured with the same schedule that failed at the same
+/// moment wakes up at the same instants, which can hammer a recovering service with synchronized
+/// bursts of retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Jitter {
+    /// Sleep for exactly the delay produced by the backoff.
+    #[default]
+    None,
+
+    /// Sleep for a random duration between zero and the delay.
+    Full,
+
+    /// Sleep for half of the delay plus a random duration between zero and the other half.
+    Equal,
+
+    /// Sleep for a random duration between the first delay and three times the previous sleep,
+    /// capped by [`Attempt::max_delay`](crate::Attempt::max_delay), like
+    /// [`DecorrelatedJitter`](crate::DecorrelatedJitter), as described in the AWS Architecture
+    /// Blog post "Exponential Backoff And Jitter".
+    ///
+    /// This replaces the schedule of the backoff rather than randomizing it: only its first
+    /// delay is used, as the lower bound of every sleep.
+    Decorrelated,
+}
+
+/// The per-run state needed to apply a [`Jitter`].
+#[derive(Debug, Clone)]
+pub(crate) struct JitterState {
+    jitter: Jitter,
+    rng: fastrand::Rng,
+
+    /// The first delay of the backoff, which [`Jitter::Decorrelated`] never goes below.
+    base: Option<Duration>,
+    previous: Option<Duration>,
+}
+
+impl JitterState {
+    pub(crate) fn new(jitter: Jitter) -> JitterState {
+        JitterState {
+            jitter,
+            rng: fastrand::Rng::new(),
+            base: None,
+            previous: None,
+        }
+    }
+
+    pub(crate) fn set_jitter(&mut self, jitter: Jitter) {
+        self.jitter = jitter;
+    }
+
+    pub(crate) fn seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    /// Randomizes `delay` according to the configured [`Jitter`], without going over `cap`.
+    pub(crate) fn apply(&mut self, delay: Duration, cap: Option<Duration>) -> Duration {
+        let mut jittered = match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => random_between(&mut self.rng, Duration::ZERO, delay),
+            Jitter::Equal => {
+                let half = delay / 2;
+                half + random_between(&mut self.rng, Duration::ZERO, delay - half)
+            }
+            Jitter::Decorrelated => {
+                let base = *self.base.get_or_insert(delay);
+                decorrelated(&mut self.rng, base, self.previous.unwrap_or(base))
+            }
+        };
+        if let Some(cap) = cap {
+            jittered = jittered.min(cap);
+        }
+        self.previous = Some(jittered);
+
+        jittered
+    }
+}
+
+/// A [`Backoff`] adapter which applies a [`Jitter`] to the delays of another backoff.
+///
+/// [`Attempt::jitter`](crate::Attempt::jitter) is usually more convenient, but wrapping a backoff
+/// directly makes the randomized schedule easy to inspect.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Backoff, Constant, DecorrelatedJitter, Exponential, Jitter, Jittered};
+/// # use std::time::Duration;
+/// let delay = Duration::from_millis(100);
+/// let mut a = Jittered::new(Constant::new(delay), Jitter::Equal).seed(7);
+/// let mut b = Jittered::new(Constant::new(delay), Jitter::Equal).seed(7);
+///
+/// for attempt in 1..10 {
+///     let jittered = a.next_delay(attempt, Duration::ZERO).unwrap();
+///     assert!(delay / 2 <= jittered && jittered <= delay);
+///
+///     // The same seed always produces the same schedule.
+///     assert_eq!(b.next_delay(attempt, Duration::ZERO), Some(jittered));
+/// }
+///
+/// // Decorrelated jitter follows the same schedule as `DecorrelatedJitter`, starting from the
+/// // first delay.
+/// let mut a = Jittered::new(Exponential::new(delay, 2.0), Jitter::Decorrelated).seed(7);
+/// let mut b = DecorrelatedJitter::new(delay, Duration::MAX).seed(7);
+///
+/// for attempt in 1..10 {
+///     let jittered = a.next_delay(attempt, Duration::ZERO).unwrap();
+///     assert!(delay <= jittered);
+///     assert_eq!(b.next_delay(attempt, Duration::ZERO), Some(jittered));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Jittered<B> {
+    backoff: B,
+    state: JitterState,
+}
+
+impl<B> Jittered<B> {
+    /// Wraps `backoff` so that each of its delays is randomized according to `jitter`.
+    pub fn new(backoff: B, jitter: Jitter) -> Jittered<B> {
+        Jittered {
+            backoff,
+            state: JitterState::new(jitter),
+        }
+    }
+
+    /// Seeds the random number generator, making the produced delays deterministic.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.state.seed(seed);
+
+        self
+    }
+}
+
+impl<B: Backoff> Backoff for Jittered<B> {
+    fn next_delay(&mut self, attempt: usize, elapsed: Duration) -> Option<Duration> {
+        self.backoff
+            .next_delay(attempt, elapsed)
+            .map(|delay| self.state.apply(delay, None))
+    }
+}
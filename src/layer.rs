@@ -0,0 +1,272 @@
+//! Tower middleware which retries the requests of a [`Service`] with an
+//! [`Attempt`](crate::Attempt).
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    AlwaysRetry, AttemptHooks, DefaultSleeper, Hooks, RetryError, RetryPolicy, RetryPredicate,
+    Sleeper,
+};
+
+/// A [`Layer`] which retries the requests of the wrapped [`Service`] following a
+/// [`RetryPolicy`], so the same retry configuration can be used for closures and service stacks.
+///
+/// Every request is retried by its own [`Attempt`](crate::Attempt). The first call is made on the
+/// service driven to readiness by [`Service::poll_ready`], and each retry on a fresh clone of it,
+/// always with a clone of the request. The predicate set with [`RetryLayer::retry_if`] and the
+/// hooks set with [`RetryLayer::hooks`] are cloned for every request too, so any state they need
+/// to share must be behind an [`Arc`](std::sync::Arc).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{RetryLayer, RetryPolicy};
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use tower::{service_fn, Layer, Service, ServiceExt};
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// let calls = Arc::new(AtomicUsize::new(0));
+/// let counter = calls.clone();
+/// let service = service_fn(move |path: &'static str| {
+///     let call = counter.fetch_add(1, Ordering::SeqCst) + 1;
+///     async move {
+///         match path {
+///             "/missing" => Err("not found"),
+///             _ if call < 3 => Err("unavailable"),
+///             _ => Ok(format!("contents of {}", path)),
+///         }
+///     }
+/// });
+///
+/// let retries = Arc::new(AtomicUsize::new(0));
+/// let counter = retries.clone();
+/// let layer = RetryLayer::new(RetryPolicy::new().delay(Duration::from_millis(100)))
+///     .retry_if(|err: &&str| *err == "unavailable")
+///     .on_retry(move |_: &&str, _, _| {
+///         counter.fetch_add(1, Ordering::SeqCst);
+///     })
+/// #   .sleeper(tokio::time::sleep)
+///     ;
+/// let service = layer.layer(service);
+///
+/// let res = service.clone().oneshot("/index.html").await;
+/// assert_eq!(res.as_deref(), Ok("contents of /index.html"));
+/// assert_eq!(retries.load(Ordering::SeqCst), 2);
+///
+/// let res = service.oneshot("/missing").await;
+/// assert_eq!(res, Err("not found"));
+/// assert_eq!(calls.load(Ordering::SeqCst), 4);
+/// #
+/// # // Services reserving capacity in `poll_ready` must be called through the ready service.
+/// # let service = service_fn(|_: ()| async { Ok::<_, &str>(()) });
+/// # let mut service = layer.layer(tower::limit::ConcurrencyLimit::new(service, 1));
+/// # for _ in 0..3 {
+/// #     service.ready().await.unwrap().call(()).await.unwrap();
+/// # }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RetryLayer<P = AlwaysRetry, H = Hooks, Z = DefaultSleeper> {
+    policy: RetryPolicy,
+    retry_if: P,
+    hooks: H,
+    sleeper: Z,
+}
+
+impl<P, H, Z> fmt::Debug for RetryLayer<P, H, Z> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryLayer")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetryLayer {
+    /// Constructs a new layer which retries every error following `policy`.
+    pub fn new(policy: RetryPolicy) -> RetryLayer {
+        RetryLayer {
+            policy,
+            retry_if: AlwaysRetry,
+            hooks: Hooks::default(),
+            sleeper: DefaultSleeper,
+        }
+    }
+}
+
+impl<P, H, Z> RetryLayer<P, H, Z> {
+    /// Sets the predicate used to decide whether an error is worth retrying. See
+    /// [`Attempt::retry_if`](crate::Attempt::retry_if).
+    pub fn retry_if<Q>(self, retry_if: Q) -> RetryLayer<Q, H, Z> {
+        RetryLayer {
+            policy: self.policy,
+            retry_if,
+            hooks: self.hooks,
+            sleeper: self.sleeper,
+        }
+    }
+
+    /// Sets the callbacks notified as requests are retried. See
+    /// [`Attempt::hooks`](crate::Attempt::hooks).
+    pub fn hooks<I>(self, hooks: I) -> RetryLayer<P, I, Z> {
+        self.map_hooks(|_| hooks)
+    }
+
+    /// Replaces the hooks with the result of applying `f` to them.
+    fn map_hooks<I>(self, f: impl FnOnce(H) -> I) -> RetryLayer<P, I, Z> {
+        RetryLayer {
+            policy: self.policy,
+            retry_if: self.retry_if,
+            hooks: f(self.hooks),
+            sleeper: self.sleeper,
+        }
+    }
+
+    /// Sets the [`Sleeper`] used between attempts, which is cloned for every request. See
+    /// [`Attempt::sleeper`](crate::Attempt::sleeper).
+    ///
+    /// Without the `tokio`, `async-std` or `smol` feature, the [`RetryService`] can only be
+    /// called once a [`Sleeper`] is set.
+    pub fn sleeper<S>(self, sleeper: S) -> RetryLayer<P, H, S>
+    where
+        S: Sleeper + Clone + Send + Sync + 'static,
+    {
+        RetryLayer {
+            policy: self.policy,
+            retry_if: self.retry_if,
+            hooks: self.hooks,
+            sleeper,
+        }
+    }
+}
+
+impl<P, R, S, G, Z> RetryLayer<P, Hooks<R, S, G>, Z> {
+    /// Sets a closure called before each retry. See
+    /// [`Attempt::on_retry`](crate::Attempt::on_retry).
+    pub fn on_retry<E, Q>(self, on_retry: Q) -> RetryLayer<P, Hooks<Q, S, G>, Z>
+    where
+        Q: FnMut(&E, usize, Duration),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry,
+            on_success: hooks.on_success,
+            on_give_up: hooks.on_give_up,
+        })
+    }
+
+    /// Sets a closure called once a request succeeds. See
+    /// [`Attempt::on_success`](crate::Attempt::on_success).
+    pub fn on_success<Q>(self, on_success: Q) -> RetryLayer<P, Hooks<R, Q, G>, Z>
+    where
+        Q: FnMut(usize),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry: hooks.on_retry,
+            on_success,
+            on_give_up: hooks.on_give_up,
+        })
+    }
+
+    /// Sets a closure called once a request fails for good. See
+    /// [`Attempt::on_give_up`](crate::Attempt::on_give_up).
+    pub fn on_give_up<E, Q>(self, on_give_up: Q) -> RetryLayer<P, Hooks<R, S, Q>, Z>
+    where
+        Q: FnMut(&RetryError<E>),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry: hooks.on_retry,
+            on_success: hooks.on_success,
+            on_give_up,
+        })
+    }
+}
+
+impl<S, P, H, Z> Layer<S> for RetryLayer<P, H, Z>
+where
+    P: Clone,
+    H: Clone,
+    Z: Clone,
+{
+    type Service = RetryService<S, P, H, Z>;
+
+    fn layer(&self, inner: S) -> RetryService<S, P, H, Z> {
+        RetryService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A [`Service`] which retries the requests of another one. See [`RetryLayer`].
+#[derive(Clone)]
+pub struct RetryService<S, P = AlwaysRetry, H = Hooks, Z = DefaultSleeper> {
+    inner: S,
+    layer: RetryLayer<P, H, Z>,
+}
+
+impl<S: fmt::Debug, P, H, Z> fmt::Debug for RetryService<S, P, H, Z> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryService")
+            .field("inner", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<S, P, H, Z, Req> Service<Req> for RetryService<S, P, H, Z>
+where
+    S: Service<Req> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Response: Send,
+    S::Error: Send,
+    Req: Clone + Send + 'static,
+    P: RetryPredicate<S::Error> + Clone + Send + 'static,
+    H: AttemptHooks<S::Error> + Clone + Send + 'static,
+    Z: Sleeper + Clone + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        // The service driven to readiness by `poll_ready` makes the first call, since it may hold
+        // a reservation, e.g. a permit of a concurrency limit. Retries call fresh clones.
+        let clone = self.inner.clone();
+        let mut ready = Some(std::mem::replace(&mut self.inner, clone));
+        let inner = self.inner.clone();
+        let attempt = self
+            .layer
+            .policy
+            .to(move || {
+                let ready = ready.take();
+                let mut service = inner.clone();
+                let req = req.clone();
+
+                async move {
+                    match ready {
+                        Some(mut ready) => ready.call(req).await,
+                        None => {
+                            std::future::poll_fn(|cx| service.poll_ready(cx)).await?;
+                            service.call(req).await
+                        }
+                    }
+                }
+            })
+            .retry_if(self.layer.retry_if.clone())
+            .hooks(self.layer.hooks.clone())
+            .sleeper(self.layer.sleeper.clone());
+
+        Box::pin(attempt.run_async())
+    }
+}
@@ -33,40 +33,173 @@
 //!     # Ok(())
 //! }
 //!
+//! # #[cfg(feature = "async-core")]
 //! async fn async_attempt_example() -> Result<Data, Error> {
 //!     Attempt::to(fetch_data_from_unreliable_api_async)
 //!         .delay(std::time::Duration::from_secs(1))
 //!         .max_tries(1000)
+//! #         .sleeper(tokio::time::sleep)
 //!         .run_async()
 //!         .await
 //! }
 //! ```
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use error::ErrorHistory;
+use jitter::JitterState;
+
+mod backoff;
+mod budget;
+mod cancel;
+mod circuit;
+mod clock;
+mod context;
+mod error;
+mod fallback;
+#[cfg(feature = "async-core")]
+mod hedge;
+mod hooks;
+mod jitter;
+#[cfg(feature = "tower")]
+mod layer;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod policy;
+mod predicate;
+mod resume;
+mod sleep;
+mod stats;
+#[cfg(feature = "async-core")]
+mod timeout;
+mod trace;
+mod until;
+
+#[cfg(feature = "macros")]
+pub use attempt_macros::attempt;
+pub use backoff::{Backoff, Constant, DecorrelatedJitter, Exponential, Fibonacci, Linear};
+pub use budget::RetryBudget;
+pub use cancel::CancelToken;
+pub use circuit::{CircuitBreaker, CircuitOpen, CircuitState, WithCircuitBreaker};
+pub use clock::{Clock, MockClock};
+pub use context::{AttemptContext, Operation, WithContext};
+pub use error::{RetryError, StopReason};
+pub use fallback::{Fallback, Staged};
+pub use hooks::{AttemptHooks, GiveUpHook, Hooks, RetryHook, SuccessHook};
+pub use jitter::{Jitter, Jittered};
+#[cfg(feature = "tower")]
+pub use layer::{RetryLayer, RetryService};
+#[cfg(feature = "metrics")]
+pub use metrics::WithMetrics;
+pub use policy::RetryPolicy;
+pub use predicate::{
+    AlwaysRetry, HonorRetryAfter, IgnoreRetryAfter, RetryAfter, RetryAfterMode, RetryDecision,
+    RetryPredicate,
+};
+pub use resume::ResumeIter;
+#[cfg(feature = "async-core")]
+pub use resume::ResumeStream;
+#[cfg(feature = "async-std")]
+pub use sleep::AsyncStdSleeper;
+pub use sleep::DefaultSleeper;
+#[cfg(feature = "async-core")]
+pub use sleep::Sleeper;
+#[cfg(feature = "smol")]
+pub use sleep::SmolSleeper;
+#[cfg(feature = "tokio")]
+pub use sleep::TokioSleeper;
+pub use stats::AttemptStats;
+#[cfg(feature = "async-core")]
+pub use timeout::{Timeout, TimeoutError, TimeoutFuture};
+#[cfg(feature = "tracing")]
+pub use trace::TraceErrors;
+pub use until::{RunUntilError, UntilError};
 
 /// This type provides an API for retrying failable functions.
 ///
 /// See the documentation for this type's methods for detailed examples and the module
 /// documentation for an overview example.
-pub struct Attempt<F> {
+pub struct Attempt<F, P = AlwaysRetry, H = Hooks, Z = DefaultSleeper, A = IgnoreRetryAfter> {
     /// The function that will be ran and retried if necessary.
     func: F,
 
-    /// The interval of time between each attempt.
-    ///
-    /// This duration will be multiplied by `delay_growth_magnitude` on each epoch.
-    /// When `delay` is [`None`], the function will be called infinitly until an [`Ok`] is
-    /// returned.
-    delay: Option<Duration>,
+    /// Classifies each error returned by `func` as either worth retrying or final.
+    retry_if: P,
+
+    /// Decides whether the delay requested by each error is waited for.
+    retry_after: A,
+
+    /// Callbacks notified as the function is retried.
+    hooks: H,
+
+    /// The schedule of delays between each attempt.
+    schedule: Schedule,
 
-    /// The magnitude of growth by which the `delay` will be multiplied by after each try.
-    delay_growth_magnitude: f32,
+    /// Randomizes the delays produced by `schedule`.
+    jitter: JitterState,
 
     /// The maximum number of tries before the function returns an error.
     ///
     /// When `max_tries` is [`None`], the function will be called infinitly until an [`Ok`] is
     /// returned.
     max_tries: Option<usize>,
+
+    /// The upper bound on any single delay between attempts.
+    max_delay: Option<Duration>,
+
+    /// The total amount of time, measured from the first call, after which no more attempts are
+    /// made.
+    deadline: Option<Duration>,
+
+    /// The number of most recent errors kept by [`Attempt::run_detailed`]. When [`None`], every
+    /// error is kept.
+    error_history: Option<usize>,
+
+    /// Stops the retry loop early once cancelled.
+    cancel: Option<CancelToken>,
+
+    /// Bounds the retries made across every [`Attempt`] sharing it.
+    budget: Option<RetryBudget>,
+
+    /// Measures time, and sleeps between the attempts of [`Attempt::run`]. When [`None`], the
+    /// system clock and [`std::thread::sleep`] are used.
+    clock: Option<Box<dyn Clock + Send + Sync>>,
+
+    /// Puts [`Attempt::run_async`] to sleep between attempts.
+    sleeper: Z,
+}
+
+/// The source of the delays between attempts.
+enum Schedule {
+    /// The exponential schedule configured by [`Attempt::delay`] and
+    /// [`Attempt::delay_growth_magnitude`]. An initial delay of zero means no delay at all.
+    Exponential(Exponential),
+
+    /// A schedule provided with [`Attempt::backoff`].
+    Custom(Box<dyn Backoff + Send>),
+}
+
+impl Schedule {
+    /// Returns the exponential schedule, replacing a custom backoff with the default one first.
+    fn exponential(&mut self) -> &mut Exponential {
+        if let Schedule::Custom(_) = self {
+            *self = Schedule::Exponential(Exponential::new(Duration::ZERO, DEFAULT_DELAY_GROWTH));
+        }
+
+        match self {
+            Schedule::Exponential(exponential) => exponential,
+            Schedule::Custom(_) => unreachable!(),
+        }
+    }
+}
+
+impl Backoff for Schedule {
+    fn next_delay(&mut self, attempt: usize, elapsed: Duration) -> Option<Duration> {
+        match self {
+            Schedule::Exponential(exponential) => exponential.next_delay(attempt, elapsed),
+            Schedule::Custom(backoff) => backoff.next_delay(attempt, elapsed),
+        }
+    }
 }
 
 /// The default magnitude by which the delay between tries increases.
@@ -84,13 +217,41 @@ impl<F> Attempt<F> {
     /// * No time delay between attempts (thread will not sleep)
     /// * A default delay growth magnitude of 1.25 (25% increase each attempt)
     /// * A cap on maximum tries of 10
+    /// * Every error is considered worth retrying
+    ///
     /// These defaults are in place to hopefully prevent any accidental infinite loops.
+    ///
+    /// # Example
+    /// The function may mutate the state it captures, e.g. to walk through a list of hosts:
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// let mut hosts = ["a.example.com", "b.example.com", "c.example.com"].into_iter();
+    ///
+    /// let res = Attempt::to(|| match hosts.next() {
+    ///     Some("c.example.com") => Ok("c.example.com"),
+    ///     Some(host) => Err(format!("{} is down", host)),
+    ///     None => Err("out of hosts".to_string()),
+    /// })
+    /// .run();
+    ///
+    /// assert_eq!(res, Ok("c.example.com"));
+    /// ```
     pub fn to(func: F) -> Attempt<F> {
         Attempt {
             func,
-            delay: None,
-            delay_growth_magnitude: DEFAULT_DELAY_GROWTH,
+            retry_if: AlwaysRetry,
+            retry_after: IgnoreRetryAfter,
+            hooks: Hooks::default(),
+            schedule: Schedule::Exponential(Exponential::new(Duration::ZERO, DEFAULT_DELAY_GROWTH)),
+            jitter: JitterState::new(Jitter::None),
             max_tries: Some(DEFAULT_MAX_TRIES),
+            max_delay: None,
+            deadline: None,
+            error_history: None,
+            cancel: None,
+            budget: None,
+            clock: None,
+            sleeper: DefaultSleeper,
         }
     }
 
@@ -101,15 +262,260 @@ impl<F> Attempt<F> {
     /// configuration outlined in the documentation for [`Attempt::to`]. Using this function is
     /// honestly a terrible idea, especially for production code, but it may be useful for
     /// prototyping, idk.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// let mut calls = 0;
+    /// let res = Attempt::infinitely(|| {
+    ///     calls += 1;
+    ///     if calls < 20 { Err(()) } else { Ok(calls) }
+    /// });
+    ///
+    /// assert_eq!(res, 20);
+    /// ```
     pub fn infinitely<T, E>(func: F) -> T
     where
-        F: Fn() -> Result<T, E>,
+        F: FnMut() -> Result<T, E>,
     {
         Attempt::to(func)
             .no_max_tries()
             .run()
             .unwrap_or_else(|_| unreachable!())
     }
+}
+
+impl<F> Attempt<WithContext<F>> {
+    /// Like [`Attempt::to`], but the function is given an [`AttemptContext`] describing the
+    /// attempt about to be made, including the error of the previous call.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// let hosts = ["primary.example.com", "secondary.example.com"];
+    ///
+    /// let res: Result<&str, String> = Attempt::with_context(|cx| {
+    ///     // Rotate through the hosts on each retry.
+    ///     let host = hosts[(cx.attempt() - 1) % hosts.len()];
+    ///     if let Some(err) = cx.previous_error() {
+    ///         assert_eq!(err, "primary.example.com is down");
+    ///     }
+    ///
+    ///     if host == "primary.example.com" {
+    ///         Err(format!("{} is down", host))
+    ///     } else {
+    ///         Ok(host)
+    ///     }
+    /// })
+    /// .run();
+    ///
+    /// assert_eq!(res, Ok("secondary.example.com"));
+    /// ```
+    pub fn with_context<E, R>(func: F) -> Attempt<WithContext<F>>
+    where
+        F: FnMut(&AttemptContext<'_, E>) -> R,
+    {
+        Attempt::to(WithContext(func))
+    }
+}
+
+impl<F, P, H, Z, A> Attempt<F, P, H, Z, A> {
+    /// Sets the predicate used to decide whether an error is worth retrying.
+    ///
+    /// The predicate is consulted after every failed call. When it returns
+    /// [`RetryDecision::Stop`] (or `false`), the error is returned immediately without sleeping,
+    /// regardless of how many tries remain. By default every error is retried.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, RetryDecision};
+    /// # use std::cell::Cell;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Error {
+    ///     Unavailable,
+    ///     NotFound,
+    /// }
+    ///
+    /// let calls = Cell::new(0);
+    /// let res: Result<(), Error> = Attempt::to(|| {
+    ///     calls.set(calls.get() + 1);
+    ///     Err(Error::NotFound)
+    /// })
+    /// .retry_if(|err: &Error| match err {
+    ///     Error::Unavailable => RetryDecision::Retry,
+    ///     Error::NotFound => RetryDecision::Stop,
+    /// })
+    /// .run();
+    ///
+    /// assert_eq!(res, Err(Error::NotFound));
+    /// assert_eq!(calls.get(), 1);
+    /// ```
+    pub fn retry_if<Q>(self, retry_if: Q) -> Attempt<F, Q, H, Z, A> {
+        self.map_retry_if(|_| retry_if)
+    }
+
+    /// Makes the delay before the next attempt at least as long as the one requested by the
+    /// error, e.g. through an HTTP `Retry-After` header. See [`RetryAfter`].
+    ///
+    /// The requested delay is only honored for errors that the predicate set with
+    /// [`Attempt::retry_if`] deems worth retrying, and it is still capped by
+    /// [`Attempt::max_delay`] and subject to [`Attempt::deadline`]. It doesn't matter whether the
+    /// predicate is set before or after this.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, RetryAfter};
+    /// # use std::time::Duration;
+    /// struct TooManyRequests {
+    ///     retry_after: Duration,
+    /// }
+    ///
+    /// impl RetryAfter for TooManyRequests {
+    ///     fn retry_after(&self) -> Option<Duration> {
+    ///         Some(self.retry_after)
+    ///     }
+    /// }
+    ///
+    /// let mut delays = Vec::new();
+    /// let res = Attempt::to(|| {
+    ///     Err::<(), _>(TooManyRequests { retry_after: Duration::from_millis(20) })
+    /// })
+    /// .delay(Duration::from_millis(10))
+    /// .delay_growth_magnitude(2.0)
+    /// .max_delay(Duration::from_millis(30))
+    /// .max_tries(4)
+    /// .honor_retry_after()
+    /// .retry_if(|_: &TooManyRequests| true)
+    /// .on_retry(|_: &TooManyRequests, _, delay| delays.push(delay))
+    /// .run();
+    ///
+    /// assert!(res.is_err());
+    /// // The server-requested 20ms wins over the first delay, and the third one is capped.
+    /// assert_eq!(delays, [20, 20, 30].map(Duration::from_millis));
+    /// ```
+    pub fn honor_retry_after(self) -> Attempt<F, P, H, Z, HonorRetryAfter> {
+        Attempt {
+            func: self.func,
+            retry_if: self.retry_if,
+            retry_after: HonorRetryAfter,
+            hooks: self.hooks,
+            schedule: self.schedule,
+            jitter: self.jitter,
+            max_tries: self.max_tries,
+            max_delay: self.max_delay,
+            deadline: self.deadline,
+            error_history: self.error_history,
+            cancel: self.cancel,
+            budget: self.budget,
+            clock: self.clock,
+            sleeper: self.sleeper,
+        }
+    }
+
+    /// Replaces the retry predicate with the result of applying `f` to it.
+    fn map_retry_if<Q>(self, f: impl FnOnce(P) -> Q) -> Attempt<F, Q, H, Z, A> {
+        Attempt {
+            func: self.func,
+            retry_if: f(self.retry_if),
+            retry_after: self.retry_after,
+            hooks: self.hooks,
+            schedule: self.schedule,
+            jitter: self.jitter,
+            max_tries: self.max_tries,
+            max_delay: self.max_delay,
+            deadline: self.deadline,
+            error_history: self.error_history,
+            cancel: self.cancel,
+            budget: self.budget,
+            clock: self.clock,
+            sleeper: self.sleeper,
+        }
+    }
+
+    /// Sets the callbacks notified as the function is retried, replacing any set with
+    /// [`Attempt::on_retry`], [`Attempt::on_success`] or [`Attempt::on_give_up`].
+    ///
+    /// This is useful to share a single [`AttemptHooks`] implementation (e.g. one which logs and
+    /// counts retries) across many call sites.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, AttemptHooks};
+    /// # use std::cell::{Cell, RefCell};
+    /// # use std::time::Duration;
+    /// #[derive(Default)]
+    /// struct Log(RefCell<Vec<String>>);
+    ///
+    /// impl AttemptHooks<&str> for &Log {
+    ///     fn on_retry(&mut self, err: &&str, attempt: usize, _delay: Duration) {
+    ///         self.0.borrow_mut().push(format!("attempt {} failed: {}", attempt, err));
+    ///     }
+    ///
+    ///     fn on_success(&mut self, attempts: usize) {
+    ///         self.0.borrow_mut().push(format!("succeeded after {} attempts", attempts));
+    ///     }
+    /// }
+    ///
+    /// let log = Log::default();
+    /// let calls = Cell::new(0);
+    /// # #[cfg(feature = "async-core")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// Attempt::to(|| async {
+    ///     calls.set(calls.get() + 1);
+    ///     if calls.get() < 2 { Err("busy") } else { Ok(()) }
+    /// })
+    /// .hooks(&log)
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_async()
+    /// .await
+    /// .unwrap();
+    ///
+    /// assert_eq!(*log.0.borrow(), ["attempt 1 failed: busy", "succeeded after 2 attempts"]);
+    /// # });
+    /// ```
+    pub fn hooks<I>(self, hooks: I) -> Attempt<F, P, I, Z, A> {
+        self.map_hooks(|_| hooks)
+    }
+
+    /// Replaces the hooks with the result of applying `f` to them.
+    fn map_hooks<I>(self, f: impl FnOnce(H) -> I) -> Attempt<F, P, I, Z, A> {
+        Attempt {
+            func: self.func,
+            retry_if: self.retry_if,
+            retry_after: self.retry_after,
+            hooks: f(self.hooks),
+            schedule: self.schedule,
+            jitter: self.jitter,
+            max_tries: self.max_tries,
+            max_delay: self.max_delay,
+            deadline: self.deadline,
+            error_history: self.error_history,
+            cancel: self.cancel,
+            budget: self.budget,
+            clock: self.clock,
+            sleeper: self.sleeper,
+        }
+    }
+
+    /// Replaces the function with the result of applying `f` to it.
+    fn map_func<G>(self, f: impl FnOnce(F) -> G) -> Attempt<G, P, H, Z, A> {
+        Attempt {
+            func: f(self.func),
+            retry_if: self.retry_if,
+            retry_after: self.retry_after,
+            hooks: self.hooks,
+            schedule: self.schedule,
+            jitter: self.jitter,
+            max_tries: self.max_tries,
+            max_delay: self.max_delay,
+            deadline: self.deadline,
+            error_history: self.error_history,
+            cancel: self.cancel,
+            budget: self.budget,
+            clock: self.clock,
+            sleeper: self.sleeper,
+        }
+    }
 
     /// Removes the limit on the maximum number of calls to the function that will be made before
     /// propagating an [`Err`].
@@ -134,18 +540,24 @@ impl<F> Attempt<F> {
     }
 
     /// Removes the delay between each call to the function.
+    ///
+    /// This also discards any backoff set with [`Attempt::backoff`].
     pub fn no_delay(mut self) -> Self {
-        self.delay = None;
+        self.schedule.exponential().set_initial(Duration::ZERO);
 
         self
     }
 
     /// Sets the duration of the delay between each call to the function.
     ///
-    /// For synchronous functions, the delay is implemented using [`std::thread::sleep`]. For
-    /// async functions, the delay uses [`tokio::time::sleep`].
+    /// For synchronous functions, the delay is implemented using [`std::thread::sleep`] (see
+    /// [`Attempt::clock`]). For async functions, the delay uses the [`Sleeper`] of the enabled
+    /// runtime (see [`Attempt::sleeper`]).
+    ///
+    /// This is a shortcut for an [`Exponential`] backoff starting at `delay`, and it discards any
+    /// backoff set with [`Attempt::backoff`].
     pub fn delay(mut self, delay: Duration) -> Self {
-        self.delay = Some(delay);
+        self.schedule.exponential().set_initial(delay);
 
         self
     }
@@ -155,84 +567,1666 @@ impl<F> Attempt<F> {
     /// call to the function fails, [`Attempt`] will wait 1 second before executing the function
     /// again. If that call also fails, [`Attempt`] will wait 2 seconds before executing the
     /// function a third time, and so on.
+    ///
+    /// Like [`Attempt::delay`], this configures an [`Exponential`] backoff and discards any
+    /// backoff set with [`Attempt::backoff`].
     pub fn delay_growth_magnitude(mut self, magnitude: f32) -> Self {
-        self.delay_growth_magnitude = magnitude;
+        self.schedule.exponential().set_factor(magnitude);
 
         self
     }
 
-    pub fn run<T, E>(self) -> Result<T, E>
+    /// Sets the strategy used to compute the delay between each call to the function, replacing
+    /// the schedule configured by [`Attempt::delay`] and [`Attempt::delay_growth_magnitude`].
+    ///
+    /// The crate ships with [`Constant`], [`Linear`], [`Exponential`], [`Fibonacci`] and
+    /// [`DecorrelatedJitter`] strategies, and any type implementing [`Backoff`] can be used.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, Fibonacci};
+    /// # use std::time::Duration;
+    /// let res: Result<(), ()> = Attempt::to(|| Err(()))
+    ///     .backoff(Fibonacci::new(Duration::from_millis(1)))
+    ///     .max_tries(5)
+    ///     .run();
+    ///
+    /// assert!(res.is_err());
+    /// ```
+    pub fn backoff<B>(mut self, backoff: B) -> Self
     where
-        F: Fn() -> Result<T, E>,
+        B: Backoff + Send + 'static,
     {
-        let execute_fn = self.func;
-        let mut delay = self.delay;
+        self.schedule = Schedule::Custom(Box::new(backoff));
 
-        for iteration in 0.. {
-            match execute_fn() {
-                Ok(res) => return Ok(res),
-                Err(err) => {
-                    if let Some(max_tries) = self.max_tries {
-                        if iteration + 1 >= max_tries {
-                            return Err(err);
-                        }
-                    }
+        self
+    }
 
-                    if let Some(epoch_delay) = delay {
-                        std::thread::sleep(epoch_delay);
+    /// Sets how the delay between each call to the function is randomized.
+    ///
+    /// Jitter spreads out the retries of many clients sharing the same configuration, which
+    /// keeps them from all waking up and hitting a recovering service at the same instant. See
+    /// [`Jitter`] for the available modes. No jitter is applied by default.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, Jitter};
+    /// # use std::time::Duration;
+    /// let res: Result<(), ()> = Attempt::to(|| Err(()))
+    ///     .delay(Duration::from_millis(1))
+    ///     .jitter(Jitter::Full)
+    ///     .jitter_seed(42)
+    ///     .max_tries(3)
+    ///     .run();
+    ///
+    /// assert!(res.is_err());
+    /// ```
+    ///
+    /// Decorrelated jitter caps each delay with [`Attempt::max_delay`] before growing the next
+    /// one from it, like [`DecorrelatedJitter`]:
+    /// ```rust
+    /// # use attempt::{Attempt, Backoff, DecorrelatedJitter, Jitter, MockClock};
+    /// # use std::time::Duration;
+    /// let (base, cap) = (Duration::from_millis(100), Duration::from_secs(1));
+    /// let clock = MockClock::new();
+    /// let res: Result<(), ()> = Attempt::to(|| Err(()))
+    ///     .delay(base)
+    ///     .max_delay(cap)
+    ///     .jitter(Jitter::Decorrelated)
+    ///     .jitter_seed(42)
+    ///     .max_tries(50)
+    ///     .clock(clock.clone())
+    ///     .run();
+    ///
+    /// assert!(res.is_err());
+    /// let mut backoff = DecorrelatedJitter::new(base, cap).seed(42);
+    /// for (attempt, delay) in clock.sleeps().into_iter().enumerate() {
+    ///     assert_eq!(backoff.next_delay(attempt + 1, Duration::ZERO), Some(delay));
+    /// }
+    /// ```
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter.set_jitter(jitter);
 
-                        delay = Some(epoch_delay.mul_f32(self.delay_growth_magnitude));
-                    }
-                }
-            }
-        }
+        self
+    }
 
-        unreachable!()
+    /// Seeds the random number generator used for jitter, making the delays deterministic.
+    ///
+    /// This is mostly useful for tests. By default, the generator is seeded randomly.
+    pub fn jitter_seed(mut self, seed: u64) -> Self {
+        self.jitter.seed(seed);
+
+        self
     }
 
-    /// Runs the asynchronous function repeatedly until it returns [`Ok`] or the maximum attempt
-    /// limit is reached, sleeping (using [`tokio::time::sleep`]) for the configured delay time if
-    /// one is set.
+    /// Caps the delay between any two calls to the function at `max_delay`.
+    ///
+    /// The cap is applied after jitter, so it is a hard upper bound on every sleep. This keeps a
+    /// growing schedule from compounding into absurdly long delays when `max_tries` is large.
     ///
     /// # Example
     /// ```rust
     /// # use attempt::Attempt;
-    /// # #[tokio::main]
+    /// # use std::time::{Duration, Instant};
+    /// let started = Instant::now();
+    /// let res: Result<(), ()> = Attempt::to(|| Err(()))
+    ///     .delay(Duration::from_millis(1))
+    ///     .delay_growth_magnitude(10.0)
+    ///     .max_delay(Duration::from_millis(5))
+    ///     .max_tries(4)
+    ///     .run();
+    ///
+    /// assert!(res.is_err());
+    /// // Without the cap, the third delay alone would have been 100ms.
+    /// assert!(started.elapsed() < Duration::from_millis(100));
+    /// ```
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+
+        self
+    }
+
+    /// Sets the total amount of time, measured from the first call to the function, that the
+    /// [`Attempt`] may spend before propagating an [`Err`].
+    ///
+    /// The deadline is checked before each delay: if sleeping would end past the deadline, the
+    /// most recent error is returned right away instead. A call which is already in progress
+    /// when the deadline passes is not interrupted.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::cell::Cell;
+    /// # use std::time::{Duration, Instant};
+    /// let calls = Cell::new(0);
+    /// let started = Instant::now();
+    /// let res: Result<(), ()> = Attempt::to(|| {
+    ///     calls.set(calls.get() + 1);
+    ///     Err(())
+    /// })
+    /// .delay(Duration::from_millis(100))
+    /// .delay_growth_magnitude(1.0)
+    /// .deadline(Duration::from_millis(250))
+    /// .no_max_tries()
+    /// .run();
+    ///
+    /// assert!(res.is_err());
+    /// // Calls are made at 0ms, 100ms and 200ms. Sleeping once more would overshoot the deadline.
+    /// assert_eq!(calls.get(), 3);
+    /// assert!(started.elapsed() < Duration::from_millis(250));
+    /// ```
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+
+        self
+    }
+
+    /// Sets how [`Attempt::run_async`] sleeps between attempts.
+    ///
+    /// By default, the sleep function of the runtime enabled through cargo features is used
+    /// (tokio, async-std or smol, in that order of preference, see [`DefaultSleeper`]). This
+    /// makes it possible to use any other runtime, or to substitute a fake sleep in tests. See
+    /// [`Sleeper`] for an example.
+    ///
+    /// Without any of these runtime features, this must be called before running the function
+    /// asynchronously, which doesn't compile otherwise.
+    #[cfg(feature = "async-core")]
+    pub fn sleeper<S>(self, sleeper: S) -> Attempt<F, P, H, S, A>
+    where
+        S: Sleeper + Send + Sync + 'static,
+    {
+        Attempt {
+            func: self.func,
+            retry_if: self.retry_if,
+            retry_after: self.retry_after,
+            hooks: self.hooks,
+            schedule: self.schedule,
+            jitter: self.jitter,
+            max_tries: self.max_tries,
+            max_delay: self.max_delay,
+            deadline: self.deadline,
+            error_history: self.error_history,
+            cancel: self.cancel,
+            budget: self.budget,
+            clock: self.clock,
+            sleeper,
+        }
+    }
+
+    /// Sets the [`Clock`] used to measure time, e.g. for [`Attempt::deadline`], and to sleep
+    /// between the attempts of [`Attempt::run`].
+    ///
+    /// This is mostly useful in tests: with a [`MockClock`], [`Attempt::run`] returns right away
+    /// whatever the delays, and the delays it slept for can be checked afterwards. See
+    /// [`MockClock`] for an example. [`Attempt::run_async`] still sleeps with its [`Sleeper`].
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        self.clock = Some(Box::new(clock));
+
+        self
+    }
+
+    /// Limits each call to the asynchronous function to `timeout`.
+    ///
+    /// A call which doesn't complete in time is cancelled by dropping its future, and counts as a
+    /// failed attempt. To tell such failures apart, the error type becomes a [`TimeoutError`]
+    /// wrapping the function's own error, so this should be set before [`Attempt::retry_if`] or
+    /// any hooks that inspect the error.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, TimeoutError};
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
     /// # async fn main() {
-    /// Attempt::to(|| async { if (true) { Ok(())  } else { Err(()) } })
-    ///     .run_async()
+    /// let started = tokio::time::Instant::now();
+    /// let res: Result<(), _> = Attempt::to(|| async {
+    ///     // This request hangs for way too long.
+    ///     tokio::time::sleep(Duration::from_secs(3600)).await;
+    ///     Err::<(), &str>("unreachable")
+    /// })
+    /// .timeout(Duration::from_secs(1))
+    /// .max_tries(3)
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_async()
+    /// .await;
+    ///
+    /// assert_eq!(res, Err(TimeoutError::TimedOut(Duration::from_secs(1))));
+    /// assert_eq!(started.elapsed(), Duration::from_secs(3));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub fn timeout(self, timeout: Duration) -> Attempt<Timeout<F>, P, H, Z, A> {
+        self.map_func(|func| Timeout::new(func, timeout))
+    }
+
+    /// Bounds the number of errors kept by [`Attempt::run_detailed`] and
+    /// [`Attempt::run_async_detailed`] to the `limit` most recent ones.
+    ///
+    /// By default, the error of every failed call is kept, except by [`Attempt::run_until`] and
+    /// [`Attempt::run_async_until`] which only keep the last one. Must be greater than 0 (checked
+    /// by assertion).
+    pub fn error_history(mut self, limit: usize) -> Self {
+        assert!(limit > 0);
+        self.error_history = Some(limit);
+
+        self
+    }
+
+    /// Makes the [`Attempt`] stop early once `token` is cancelled.
+    ///
+    /// Cancellation is checked between attempts and interrupts any ongoing delay right away, in
+    /// both [`Attempt::run`] and [`Attempt::run_async`]. The error of the last call is then
+    /// returned, and [`Attempt::run_detailed`] reports [`StopReason::Cancelled`]. See
+    /// [`CancelToken`] for an example.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, CancelToken, StopReason};
+    /// # use std::time::Duration;
+    /// # #[cfg(not(feature = "async-core"))]
+    /// # fn main() {}
+    /// # #[cfg(feature = "async-core")]
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// let token = CancelToken::new();
+    /// let canceller = token.clone();
+    /// tokio::spawn(async move {
+    ///     tokio::time::sleep(Duration::from_secs(5)).await;
+    ///     canceller.cancel();
+    /// });
+    ///
+    /// let started = tokio::time::Instant::now();
+    /// let err = Attempt::to(|| async { Err::<(), _>("unavailable") })
+    ///     .delay(Duration::from_secs(3600))
+    ///     .cancel_token(token)
+    /// #   .sleeper(tokio::time::sleep)
+    ///     .run_async_detailed()
     ///     .await
-    ///     .expect("should retry until an Ok is produced");
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(err.reason(), StopReason::Cancelled);
+    /// assert_eq!(*err.last(), "unavailable");
+    /// assert_eq!(started.elapsed(), Duration::from_secs(5));
     /// # }
     /// ```
-    #[cfg(feature = "async")]
-    pub async fn run_async<Fut, T, E>(self) -> Result<T, E>
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+
+        self
+    }
+
+    /// Guards the function with `breaker`, which is shared with every other [`Attempt`] using
+    /// it: while the circuit is open, the [`Attempt`] gives up with [`CircuitOpen`] (converted
+    /// into the error type of the function) instead of calling the function, and
+    /// [`Attempt::run_detailed`] reports [`StopReason::Rejected`]. See [`CircuitBreaker`].
+    ///
+    /// Since this wraps the current hooks, it must be called after [`Attempt::hooks`],
+    /// [`Attempt::on_retry`], [`Attempt::on_success`] and [`Attempt::on_give_up`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, CircuitBreaker};
+    /// # use std::io;
+    /// # use std::time::Duration;
+    /// # #[cfg(feature = "tokio")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let breaker = CircuitBreaker::new().window(4).failure_rate(0.75);
+    ///
+    /// let mut handles = Vec::new();
+    /// for _ in 0..8 {
+    ///     let breaker = breaker.clone();
+    ///     handles.push(tokio::spawn(
+    ///         Attempt::to(|| async { Err::<(), _>(io::Error::from(io::ErrorKind::TimedOut)) })
+    ///             .delay(Duration::from_millis(1))
+    ///             .circuit_breaker(breaker)
+    /// #             .sleeper(tokio::time::sleep)
+    ///             .run_async_detailed(),
+    ///     ));
+    /// }
+    ///
+    /// // Once the circuit opened, runs gave up long before their 10 tries.
+    /// for handle in handles {
+    ///     assert!(handle.await.unwrap().unwrap_err().attempts() < 10);
+    /// }
+    /// # });
+    /// ```
+    pub fn circuit_breaker(
+        self,
+        breaker: CircuitBreaker,
+    ) -> Attempt<F, P, WithCircuitBreaker<H>, Z, A> {
+        self.map_hooks(|hooks| WithCircuitBreaker::new(hooks, breaker))
+    }
+
+    /// Records the errors of the function on the spans opened with the `tracing` feature, which
+    /// requires them to implement [`Display`](std::fmt::Display). See [`TraceErrors`].
+    ///
+    /// Every run opens a `retry` span, with a child `attempt` span for each call. Without this,
+    /// the spans describe the calls and the outcome of the run but not the errors.
+    ///
+    /// Since this wraps the current hooks, it must be called after [`Attempt::hooks`],
+    /// [`Attempt::on_retry`], [`Attempt::on_success`] and [`Attempt::on_give_up`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::io::Write;
+    /// # use std::sync::{Arc, Mutex};
+    /// # use std::time::Duration;
+    /// # use tracing_subscriber::fmt::format::FmtSpan;
+    /// # #[derive(Clone, Default)]
+    /// # struct Logs(Arc<Mutex<Vec<u8>>>);
+    /// # impl Write for Logs {
+    /// #     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    /// #         self.0.lock().unwrap().write(buf)
+    /// #     }
+    /// #     fn flush(&mut self) -> std::io::Result<()> {
+    /// #         Ok(())
+    /// #     }
+    /// # }
+    /// let logs = Logs::default();
+    /// let writer = logs.clone();
+    /// let subscriber = tracing_subscriber::fmt()
+    ///     .with_writer(move || writer.clone())
+    ///     .with_span_events(FmtSpan::CLOSE)
+    ///     .without_time()
+    ///     .with_ansi(false)
+    ///     .finish();
+    ///
+    /// let mut calls = 0;
+    /// tracing::subscriber::with_default(subscriber, || {
+    ///     Attempt::to(|| {
+    ///         calls += 1;
+    ///         if calls < 3 { Err(format!("failure #{}", calls)) } else { Ok(()) }
+    ///     })
+    ///     .delay(Duration::from_millis(1))
+    ///     .delay_growth_magnitude(2.0)
+    ///     .trace_errors()
+    ///     .run()
+    /// })
+    /// .unwrap();
+    ///
+    /// let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    /// let spans: Vec<_> = logs.lines().collect();
+    /// assert_eq!(spans.len(), 4);
+    /// assert!(spans[0].contains("attempt{attempt=1 error=failure #1 delay=1ms outcome=retry}"));
+    /// assert!(spans[1].contains("attempt{attempt=2 error=failure #2 delay=2ms outcome=retry}"));
+    /// assert!(spans[2].contains("attempt{attempt=3 outcome=success}"));
+    /// assert!(spans[3].contains("retry{attempts=3 outcome=success}"));
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn trace_errors(self) -> Attempt<F, P, TraceErrors<H>, Z, A> {
+        self.map_hooks(TraceErrors::new)
+    }
+
+    /// Reports every run to the installed [metrics](https://docs.rs/metrics) recorder, labeled
+    /// with the name of the `operation`. See [`WithMetrics`] for the metrics emitted.
+    ///
+    /// Since this wraps the current hooks, it must be called after [`Attempt::hooks`],
+    /// [`Attempt::on_retry`], [`Attempt::on_success`] and [`Attempt::on_give_up`].
+    #[cfg(feature = "metrics")]
+    pub fn metrics(
+        self,
+        operation: impl Into<::metrics::SharedString>,
+    ) -> Attempt<F, P, WithMetrics<H>, Z, A> {
+        let operation = operation.into();
+
+        self.map_hooks(|hooks| WithMetrics::new(hooks, operation))
+    }
+
+    /// Draws the retries of this [`Attempt`] from `budget`, which is shared with every other
+    /// [`Attempt`] using it.
+    ///
+    /// Each run, and each iteration of [`Attempt::resume_iter`] or [`Attempt::resume_stream`],
+    /// counts as a request toward the budget, while every retry or re-creation of a source draws
+    /// from it. Once it is exhausted, the error is returned right away even if more tries remain,
+    /// and [`Attempt::run_detailed`] reports [`StopReason::BudgetExhausted`]. See
+    /// [`RetryBudget`] for an example.
+    pub fn retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.budget = Some(budget);
+
+        self
+    }
+
+    /// Applies every setting of `policy`, replacing the ones configured so far (including any
+    /// backoff set with [`Attempt::backoff`]).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, RetryPolicy};
+    /// # use std::time::Duration;
+    /// let policy = RetryPolicy::new().no_delay().max_tries(2);
+    ///
+    /// let mut retries = 0;
+    /// let res = Attempt::to(|| Err::<(), _>("unavailable"))
+    ///     .policy(&policy)
+    ///     .on_retry(|_: &&str, _, _| retries += 1)
+    ///     .run();
+    ///
+    /// assert!(res.is_err());
+    /// assert_eq!(retries, 1);
+    /// ```
+    pub fn policy(mut self, policy: &RetryPolicy) -> Self {
+        let schedule = self.schedule.exponential();
+        schedule.set_initial(policy.delay);
+        schedule.set_factor(policy.delay_growth_magnitude);
+
+        self.jitter.set_jitter(policy.jitter);
+        self.max_tries = policy.max_tries;
+        self.max_delay = policy.max_delay;
+        self.deadline = policy.deadline;
+
+        self
+    }
+
+    /// Decides what happens after the `attempt`th call to the function failed with `err`.
+    ///
+    /// Returns the delay to sleep for before trying again, or the reason to give up and return
+    /// the error.
+    fn next_delay<E>(
+        &mut self,
+        attempt: usize,
+        err: &E,
+        started: Instant,
+    ) -> Result<Duration, StopReason>
     where
-        F: Fn() -> Fut,
-        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
     {
-        let execute_fn = self.func;
-        let mut delay = self.delay;
+        let mut min_delay = match self.retry_if.decide(err) {
+            RetryDecision::Retry => Duration::ZERO,
+            RetryDecision::RetryAfter(min_delay) => min_delay,
+            RetryDecision::Stop => return Err(StopReason::NonRetryable),
+        };
+        if let Some(requested) = self.retry_after.min_delay(err) {
+            min_delay = min_delay.max(requested);
+        }
 
-        for iteration in 0.. {
-            match execute_fn().await {
-                Ok(res) => return Ok(res),
-                Err(err) => {
-                    if let Some(max_tries) = self.max_tries {
-                        if iteration + 1 >= max_tries {
-                            return Err(err);
-                        }
-                    }
+        if let Some(max_tries) = self.max_tries {
+            if attempt >= max_tries {
+                return Err(StopReason::MaxTries);
+            }
+        }
 
-                    if let Some(epoch_delay) = delay {
-                        tokio::time::sleep(epoch_delay).await;
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(StopReason::Cancelled);
+        }
 
-                        delay = Some(epoch_delay.mul_f32(self.delay_growth_magnitude));
-                    }
-                }
+        let elapsed = self.elapsed(started);
+        let delay = self
+            .schedule
+            .next_delay(attempt, elapsed)
+            .ok_or(StopReason::BackoffExhausted)?;
+        let mut delay = self.jitter.apply(delay, self.max_delay).max(min_delay);
+
+        if let Some(max_delay) = self.max_delay {
+            delay = delay.min(max_delay);
+        }
+
+        if let Some(deadline) = self.deadline {
+            if elapsed.saturating_add(delay) > deadline {
+                return Err(StopReason::Deadline);
+            }
+        }
+
+        if self
+            .budget
+            .as_ref()
+            .is_some_and(|budget| !budget.try_withdraw())
+        {
+            return Err(StopReason::BudgetExhausted);
+        }
+
+        Ok(delay)
+    }
+
+    /// Blocks the current thread for `delay` between two attempts.
+    ///
+    /// Returns `false` if the [`Attempt`] was cancelled in the meantime.
+    fn sleep(&self, delay: Duration) -> bool {
+        if let Some(clock) = &self.clock {
+            clock.sleep(delay);
+
+            return !self.cancel.as_ref().is_some_and(CancelToken::is_cancelled);
+        }
+
+        match &self.cancel {
+            Some(token) => token.sleep(delay),
+            None => {
+                if !delay.is_zero() {
+                    std::thread::sleep(delay);
+                }
+
+                true
+            }
+        }
+    }
+
+    /// Returns the current time according to the [`Clock`].
+    fn now(&self) -> Instant {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+
+    /// Returns the time which passed since `started`, according to the [`Clock`].
+    fn elapsed(&self, started: Instant) -> Duration {
+        self.now().saturating_duration_since(started)
+    }
+
+    /// Adds a fallback, tried with its own retry settings once this [`Attempt`] gave up.
+    ///
+    /// The fallback receives the error this [`Attempt`] gave up with, and returns the
+    /// [`Attempt`] to run instead, e.g. one which calls a secondary service or reads from a
+    /// cache. More fallbacks can be chained, and the chain is run with [`Fallback::run`] or
+    /// [`Fallback::run_async`]. See [`Fallback`] for examples.
+    pub fn fallback<G>(self, fallback: G) -> Fallback<Self, G> {
+        Fallback::new(self, fallback, 1)
+    }
+
+    /// Turns the function into an iterator which retries a failing source from the last item it
+    /// yielded, e.g. to resume walking a paginated API where it left off.
+    ///
+    /// The function is called with the last item yielded (or [`None`] at first) and returns the
+    /// source, any [`IntoIterator`] of [`Result`]s. Its items are yielded as they come, and when
+    /// it yields an error, the function is called again with the last item after sleeping as
+    /// usual. Once one of the limits is reached, the error is yielded and iteration stops.
+    ///
+    /// The limits apply to consecutive failures: [`Attempt::max_tries`] bounds the number of
+    /// times the source can fail without yielding an item in between, and [`Attempt::deadline`]
+    /// bounds the time spent without getting a new item.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::cell::Cell;
+    /// let resets = Cell::new(0);
+    /// let resets = &resets;
+    ///
+    /// let items = Attempt::to(move |last: Option<&u32>| {
+    ///     let first = last.map_or(1, |last| last + 1);
+    ///     (first..=5).map(move |n| {
+    ///         if n == 3 && resets.get() == 0 {
+    ///             resets.set(1);
+    ///             Err("connection reset")
+    ///         } else {
+    ///             Ok(n)
+    ///         }
+    ///     })
+    /// })
+    /// .resume_iter()
+    /// .collect::<Result<Vec<_>, _>>();
+    ///
+    /// assert_eq!(items, Ok(vec![1, 2, 3, 4, 5]));
+    /// assert_eq!(resets.get(), 1);
+    /// ```
+    pub fn resume_iter<S, T>(self) -> ResumeIter<F, P, H, S, T, Z, A>
+    where
+        F: FnMut(Option<&T>) -> S,
+        S: IntoIterator,
+    {
+        ResumeIter::new(self)
+    }
+
+    /// Like [`Attempt::resume_iter`], but for a source which is a
+    /// [`Stream`](futures_core::Stream).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use futures::{stream, TryStreamExt};
+    /// # use std::cell::Cell;
+    /// # futures::executor::block_on(async {
+    /// let resets = Cell::new(0);
+    /// let resets = &resets;
+    ///
+    /// let items: Vec<u32> = Attempt::to(move |last: Option<&u32>| {
+    ///     let first = last.map_or(1, |last| last + 1);
+    ///     stream::iter((first..=5).map(move |n| {
+    ///         if n == 3 && resets.get() == 0 {
+    ///             resets.set(1);
+    ///             Err("connection reset")
+    ///         } else {
+    ///             Ok(n)
+    ///         }
+    ///     }))
+    /// })
+    /// # .sleeper(|_| std::future::ready(()))
+    /// .resume_stream()
+    /// .try_collect()
+    /// .await
+    /// .unwrap();
+    ///
+    /// assert_eq!(items, [1, 2, 3, 4, 5]);
+    /// assert_eq!(resets.get(), 1);
+    /// # });
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub fn resume_stream<S, T, E>(self) -> ResumeStream<F, P, H, S, T, E, Z, A>
+    where
+        F: FnMut(Option<&T>) -> S,
+        S: futures_core::Stream<Item = Result<T, E>>,
+    {
+        ResumeStream::new(self)
+    }
+
+    /// Runs the function repeatedly until it returns [`Ok`] or one of the limits is reached,
+    /// sleeping (using [`std::thread::sleep`]) for the configured delay time if one is set.
+    ///
+    /// Only the error of the final call is returned. Use [`Attempt::run_detailed`] to find out
+    /// about the earlier failures too.
+    pub fn run<T, E>(self) -> Result<T, E>
+    where
+        F: Operation<E, Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+    {
+        self.error_history(1)
+            .run_detailed()
+            .map_err(RetryError::into_last)
+    }
+
+    /// Like [`Attempt::run`], but returns a [`RetryError`] describing every failed call and why
+    /// the [`Attempt`] gave up.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, StopReason};
+    /// # use std::cell::Cell;
+    /// let calls = Cell::new(0);
+    /// let err = Attempt::to(|| {
+    ///     calls.set(calls.get() + 1);
+    ///     Err::<(), _>(format!("failure #{}", calls.get()))
+    /// })
+    /// .max_tries(3)
+    /// .run_detailed()
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(err.attempts(), 3);
+    /// assert_eq!(err.reason(), StopReason::MaxTries);
+    /// assert_eq!(err.last(), "failure #3");
+    /// assert_eq!(err.into_errors(), ["failure #1", "failure #2", "failure #3"]);
+    /// ```
+    ///
+    /// With a bounded history, only the most recent errors are kept:
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::cell::Cell;
+    /// let calls = Cell::new(0);
+    /// let err = Attempt::to(|| {
+    ///     calls.set(calls.get() + 1);
+    ///     Err::<(), _>(calls.get())
+    /// })
+    /// .error_history(2)
+    /// .run_detailed()
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(err.attempts(), 10);
+    /// assert_eq!(err.into_errors(), [9, 10]);
+    /// ```
+    pub fn run_detailed<T, E>(self) -> Result<T, RetryError<E>>
+    where
+        F: Operation<E, Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+    {
+        self.run_recorded(None)
+    }
+
+    /// Like [`Attempt::run`], but also returns [`AttemptStats`] about the run, e.g. how long was
+    /// spent sleeping and how long each call took. See [`AttemptStats`] for an example.
+    pub fn run_with_stats<T, E>(self) -> (Result<T, E>, AttemptStats)
+    where
+        F: Operation<E, Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+    {
+        let mut stats = AttemptStats::default();
+        let res = self
+            .error_history(1)
+            .run_recorded(Some(&mut stats))
+            .map_err(RetryError::into_last);
+
+        (res, stats)
+    }
+
+    /// Keeps calling the function until it returns a value satisfying `condition`, e.g. to poll
+    /// the status of a job until it completes.
+    ///
+    /// Values which don't satisfy the condition are retried like errors, with the configured
+    /// delays and limits. The predicate and hooks see them as [`UntilError::Unsatisfied`], and
+    /// the errors of the function as [`UntilError::Failed`]. When the [`Attempt`] gives up, the
+    /// returned [`RunUntilError`] tells why, along with the last value returned by the function.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, StopReason, UntilError};
+    /// #[derive(Debug, PartialEq)]
+    /// enum Status {
+    ///     Pending(u32),
+    ///     Completed,
+    /// }
+    ///
+    /// let mut polls = 0;
+    /// let status = Attempt::to(|| {
+    ///     polls += 1;
+    ///     if polls < 3 { Ok::<_, &str>(Status::Pending(polls)) } else { Ok(Status::Completed) }
+    /// })
+    /// .no_delay()
+    /// .run_until(|status| *status == Status::Completed)
+    /// .unwrap();
+    ///
+    /// assert_eq!((status, polls), (Status::Completed, 3));
+    ///
+    /// // The last value is kept even when the last call failed.
+    /// let mut polls = 0;
+    /// let err = Attempt::to(|| {
+    ///     polls += 1;
+    ///     if polls < 5 { Ok(Status::Pending(polls)) } else { Err("unavailable") }
+    /// })
+    /// .no_delay()
+    /// .max_tries(5)
+    /// .run_until(|status| *status == Status::Completed)
+    /// .unwrap_err();
+    ///
+    /// assert_eq!(err.reason(), StopReason::MaxTries);
+    /// assert_eq!(err.last_value(), Some(&Status::Pending(4)));
+    /// assert_eq!(err.error().last(), &UntilError::Failed("unavailable"));
+    /// assert_eq!(err.error().errors().len(), 1);
+    /// ```
+    pub fn run_until<T, E, C>(self, condition: C) -> Result<T, RunUntilError<T, E>>
+    where
+        F: Operation<E, Output = Result<T, E>>,
+        P: RetryPredicate<UntilError<E>>,
+        A: RetryAfterMode<UntilError<E>>,
+        H: AttemptHooks<UntilError<E>>,
+        C: FnMut(&T) -> bool,
+    {
+        let limit = self.error_history.unwrap_or(1);
+        let mut condition = until::Condition::new(condition);
+        let res = self
+            .error_history(limit)
+            .map_func(|func| until::Until::new(func, &mut condition))
+            .run_detailed();
+
+        res.map_err(|err| RunUntilError::new(err, condition.take_last_value()))
+    }
+
+    /// Runs the function like [`Attempt::run_detailed`], recording the run into `stats` if
+    /// given.
+    fn run_recorded<T, E>(
+        mut self,
+        mut stats: Option<&mut AttemptStats>,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: Operation<E, Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+    {
+        let started = self.now();
+        let mut errors = ErrorHistory::new(self.error_history);
+        let mut previous_delay = None;
+        if let Some(budget) = &self.budget {
+            budget.deposit();
+        }
+
+        let run_span = trace::Span::run();
+
+        for attempt in 1.. {
+            if let Err(err) = self.hooks.before_attempt(attempt) {
+                errors.push(err);
+                let err = errors.finish(attempt - 1, self.elapsed(started), StopReason::Rejected);
+                run_span.record_outcome(Some(attempt - 1), &StopReason::Rejected);
+                run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                return Err(err);
+            }
+
+            let span = run_span.attempt(attempt);
+            let called = self.now();
+            let elapsed = self.elapsed(started);
+            let res = span.in_scope(|| {
+                self.func.call(&AttemptContext {
+                    attempt,
+                    elapsed,
+                    previous_delay,
+                    previous_error: errors.last(),
+                    #[cfg(feature = "async-core")]
+                    sleeper: None,
+                })
+            });
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.record_attempt(self.elapsed(called), self.elapsed(started));
+            }
+
+            match res {
+                Ok(res) => {
+                    span.record_outcome(None, &"success");
+                    run_span.record_outcome(Some(attempt), &"success");
+                    span.in_scope(|| self.hooks.on_success(attempt));
+
+                    return Ok(res);
+                }
+                Err(err) => {
+                    let next = span.in_scope(|| {
+                        let next = self.next_delay(attempt, &err, started);
+                        if let Ok(delay) = next {
+                            self.hooks.on_retry(&err, attempt, delay);
+                        }
+
+                        next
+                    });
+                    errors.push(err);
+
+                    let reason = match next {
+                        Ok(delay) => {
+                            span.record_retry(delay);
+
+                            previous_delay = Some(delay);
+                            let sleeping = self.now();
+                            let slept = self.sleep(delay);
+                            if let Some(stats) = stats.as_deref_mut() {
+                                let elapsed = self.elapsed(started);
+                                stats.record_sleep(delay, self.elapsed(sleeping), elapsed);
+                            }
+
+                            if slept {
+                                continue;
+                            }
+
+                            StopReason::Cancelled
+                        }
+                        Err(reason) => {
+                            span.record_outcome(None, &reason);
+
+                            reason
+                        }
+                    };
+
+                    let err = errors.finish(attempt, self.elapsed(started), reason);
+                    run_span.record_outcome(Some(attempt), &reason);
+                    run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                    return Err(err);
+                }
             }
         }
 
         unreachable!()
     }
+
+    /// Runs the asynchronous function repeatedly until it returns [`Ok`] or the maximum attempt
+    /// limit is reached, sleeping (using the [`Sleeper`] set with [`Attempt::sleeper`] or the one
+    /// of the enabled runtime feature) for the configured delay time if one is set.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// Attempt::to(|| async { if (true) { Ok(())  } else { Err(()) } })
+    /// #     .sleeper(tokio::time::sleep)
+    ///     .run_async()
+    ///     .await
+    ///     .expect("should retry until an Ok is produced");
+    /// # fn assert_send(_: impl Send) {}
+    /// # assert_send(Attempt::to(|| async { Ok::<(), ()>(()) }).sleeper(tokio::time::sleep).run_async());
+    /// # }
+    /// ```
+    ///
+    /// Errors rejected by the predicate set with [`Attempt::retry_if`] are returned right away:
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let calls = AtomicUsize::new(0);
+    /// let res: Result<(), &str> = Attempt::to(|| async {
+    ///     calls.fetch_add(1, Ordering::SeqCst);
+    ///     Err("permanent")
+    /// })
+    /// .delay(std::time::Duration::from_secs(60))
+    /// .retry_if(|err: &&str| *err != "permanent")
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_async()
+    /// .await;
+    ///
+    /// assert_eq!(res, Err("permanent"));
+    /// assert_eq!(calls.load(Ordering::SeqCst), 1);
+    /// # }
+    /// ```
+    ///
+    /// Stateful functions are supported too, as long as the futures they return don't borrow
+    /// from the function itself:
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut calls = 0;
+    /// let res: Result<usize, usize> = Attempt::to(|| {
+    ///     calls += 1;
+    ///     let call = calls;
+    ///     async move { if call < 3 { Err(call) } else { Ok(call) } }
+    /// })
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_async()
+    /// .await;
+    ///
+    /// assert_eq!(res, Ok(3));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_async<Fut, T, E>(self) -> Result<T, E>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        self.error_history(1)
+            .run_async_detailed()
+            .await
+            .map_err(RetryError::into_last)
+    }
+
+    /// Like [`Attempt::run_async`], but returns a [`RetryError`] describing every failed call
+    /// and why the [`Attempt`] gave up.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, StopReason};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let err = Attempt::to(|| async { Err::<(), _>("not found") })
+    ///     .retry_if(|_: &&str| false)
+    /// #     .sleeper(tokio::time::sleep)
+    ///     .run_async_detailed()
+    ///     .await
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(err.attempts(), 1);
+    /// assert_eq!(err.reason(), StopReason::NonRetryable);
+    /// assert_eq!(err.to_string(), format!(
+    ///     "gave up after 1 attempt in {:?} (error is not retryable): not found",
+    ///     err.elapsed(),
+    /// ));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_async_detailed<Fut, T, E>(self) -> Result<T, RetryError<E>>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        self.run_async_recorded(None).await
+    }
+
+    /// Like [`Attempt::run_async`], but also returns [`AttemptStats`] about the run, e.g. how
+    /// long was spent sleeping and how long each call took.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (res, stats) = Attempt::to(|| async { Err::<(), _>("unavailable") })
+    ///     .delay(Duration::from_millis(10))
+    ///     .delay_growth_magnitude(2.0)
+    ///     .max_tries(3)
+    /// #     .sleeper(tokio::time::sleep)
+    ///     .run_async_with_stats()
+    ///     .await;
+    ///
+    /// assert_eq!(res, Err("unavailable"));
+    /// assert_eq!(stats.attempts(), 3);
+    /// assert_eq!(stats.delays(), [10, 20].map(Duration::from_millis));
+    /// assert!(stats.sleep_time() >= Duration::from_millis(30));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_async_with_stats<Fut, T, E>(self) -> (Result<T, E>, AttemptStats)
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        let mut stats = AttemptStats::default();
+        let res = self
+            .error_history(1)
+            .run_async_recorded(Some(&mut stats))
+            .await
+            .map_err(RetryError::into_last);
+
+        (res, stats)
+    }
+
+    /// Keeps calling the asynchronous function until it returns a value satisfying `condition`,
+    /// like [`Attempt::run_until`].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut polls = 0;
+    /// let progress = Attempt::to(|| {
+    ///     polls += 1;
+    ///     let progress = polls * 25;
+    ///     async move { Ok::<_, &str>(progress) }
+    /// })
+    /// .no_delay()
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_async_until(|progress| *progress >= 100)
+    /// .await
+    /// .unwrap();
+    ///
+    /// assert_eq!(progress, 100);
+    /// # fn assert_send(_: impl Send) {}
+    /// # assert_send(Attempt::to(|| async { Ok::<_, ()>(0) }).sleeper(tokio::time::sleep).run_async_until(|n| *n > 0));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_async_until<Fut, T, E, C>(self, condition: C) -> Result<T, RunUntilError<T, E>>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<UntilError<E>>,
+        A: RetryAfterMode<UntilError<E>>,
+        H: AttemptHooks<UntilError<E>>,
+        Z: Sleeper + Send + Sync,
+        C: FnMut(&T) -> bool,
+    {
+        let limit = self.error_history.unwrap_or(1);
+        let condition =
+            std::sync::Arc::new(std::sync::Mutex::new(until::Condition::new(condition)));
+        let res = self
+            .error_history(limit)
+            .map_func(|func| until::UntilAsync::new(func, condition.clone()))
+            .run_async_detailed()
+            .await;
+
+        res.map_err(|err| {
+            let mut condition = condition
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+            RunUntilError::new(err, condition.take_last_value())
+        })
+    }
+
+    /// Runs the asynchronous function like [`Attempt::run_async_detailed`], recording the run
+    /// into `stats` if given.
+    #[cfg(feature = "async-core")]
+    async fn run_async_recorded<Fut, T, E>(
+        mut self,
+        mut stats: Option<&mut AttemptStats>,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        let started = self.now();
+        let mut errors = ErrorHistory::new(self.error_history);
+        let mut previous_delay = None;
+        if let Some(budget) = &self.budget {
+            budget.deposit();
+        }
+
+        let run_span = trace::Span::run();
+
+        for attempt in 1.. {
+            if let Err(err) = self.hooks.before_attempt(attempt) {
+                errors.push(err);
+                let err = errors.finish(attempt - 1, self.elapsed(started), StopReason::Rejected);
+                run_span.record_outcome(Some(attempt - 1), &StopReason::Rejected);
+                run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                return Err(err);
+            }
+
+            let span = run_span.attempt(attempt);
+            let called = self.now();
+            let elapsed = self.elapsed(started);
+            let res = span.in_scope(|| {
+                self.func.call(&AttemptContext {
+                    attempt,
+                    elapsed,
+                    previous_delay,
+                    previous_error: errors.last(),
+                    sleeper: Some(&self.sleeper),
+                })
+            });
+            let res = span.instrument(res).await;
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.record_attempt(self.elapsed(called), self.elapsed(started));
+            }
+
+            match res {
+                Ok(res) => {
+                    span.record_outcome(None, &"success");
+                    run_span.record_outcome(Some(attempt), &"success");
+                    span.in_scope(|| self.hooks.on_success(attempt));
+
+                    return Ok(res);
+                }
+                Err(err) => {
+                    let next = span.in_scope(|| {
+                        let next = self.next_delay(attempt, &err, started);
+                        if let Ok(delay) = next {
+                            self.hooks.on_retry(&err, attempt, delay);
+                        }
+
+                        next
+                    });
+                    errors.push(err);
+
+                    let reason = match next {
+                        Ok(delay) => {
+                            span.record_retry(delay);
+
+                            previous_delay = Some(delay);
+                            let sleeping = self.now();
+                            let slept = sleep::sleep_until_cancelled(
+                                &self.sleeper,
+                                self.cancel.as_ref(),
+                                delay,
+                            )
+                            .await;
+                            if let Some(stats) = stats.as_deref_mut() {
+                                let elapsed = self.elapsed(started);
+                                stats.record_sleep(delay, self.elapsed(sleeping), elapsed);
+                            }
+
+                            if slept {
+                                continue;
+                            }
+
+                            StopReason::Cancelled
+                        }
+                        Err(reason) => {
+                            span.record_outcome(None, &reason);
+
+                            reason
+                        }
+                    };
+
+                    let err = errors.finish(attempt, self.elapsed(started), reason);
+                    run_span.record_outcome(Some(attempt), &reason);
+                    run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                    return Err(err);
+                }
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// Like [`Attempt::run_async`], but hedges slow calls: while no call completed, another one
+    /// is started every `hedge_delay`, up to `max_in_flight` concurrent calls. The first [`Ok`]
+    /// is returned and the other calls are dropped.
+    ///
+    /// Calls are numbered in the order they are started, and every call counts toward
+    /// [`Attempt::max_tries`]. When a call fails with an error worth retrying, its replacement is
+    /// started after the delay [`Attempt::run_async`] would sleep for after that call, even if
+    /// other calls are still in flight, and no hedges are made while it is on its way. The
+    /// number of the failed call is the attempt given to the backoff and to
+    /// [`AttemptHooks::on_retry`]. A call failing while no replacement can be started, because
+    /// one is already on its way or a limit was reached, is reported to
+    /// [`AttemptHooks::on_retry`] with a zero delay as long as other calls can take over. An
+    /// error which isn't worth retrying is returned right away.
+    ///
+    /// Hedges are drawn from the [`RetryBudget`] like retries, once the hooks accepted them in
+    /// [`AttemptHooks::before_attempt`]. Once the hooks reject a call, its error is kept in the
+    /// [`RetryError`], no further calls are started, and the [`Attempt`] gives up as soon as the
+    /// calls in flight failed.
+    ///
+    /// `hedge_delay` must not be zero and `max_in_flight` must be greater than 0 (checked by
+    /// assertions).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// let started = tokio::time::Instant::now();
+    ///
+    /// let mut calls = 0;
+    /// let res = Attempt::to(|| {
+    ///     calls += 1;
+    ///     let call = calls;
+    ///     async move {
+    ///         // The first replica is stuck, the second one answers quickly.
+    ///         let latency = if call == 1 { 10 } else { 1 };
+    ///         tokio::time::sleep(Duration::from_secs(latency)).await;
+    ///         Ok::<_, ()>(call)
+    ///     }
+    /// })
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_hedged(Duration::from_secs(2), 2)
+    /// .await;
+    ///
+    /// assert_eq!(res, Ok(2));
+    /// assert_eq!(started.elapsed(), Duration::from_secs(3));
+    /// # fn assert_send(_: impl Send) {}
+    /// # assert_send(Attempt::to(|| async { Ok::<(), ()>(()) }).sleeper(tokio::time::sleep).run_hedged(Duration::MAX, 2));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_hedged<Fut, T, E>(
+        self,
+        hedge_delay: Duration,
+        max_in_flight: usize,
+    ) -> Result<T, E>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        self.error_history(1)
+            .run_hedged_detailed(hedge_delay, max_in_flight)
+            .await
+            .map_err(RetryError::into_last)
+    }
+
+    /// Like [`Attempt::run_hedged`], but returns a [`RetryError`] describing every failed call
+    /// and why the [`Attempt`] gave up.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, StopReason};
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// let err = Attempt::to(|| async {
+    ///     tokio::time::sleep(Duration::from_secs(5)).await;
+    ///     Err::<(), _>("unavailable")
+    /// })
+    /// .max_tries(3)
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_hedged_detailed(Duration::from_secs(1), 2)
+    /// .await
+    /// .unwrap_err();
+    ///
+    /// // Two calls in flight at once, then a third one as soon as the first one failed.
+    /// assert_eq!(err.attempts(), 3);
+    /// assert_eq!(err.reason(), StopReason::MaxTries);
+    /// assert_eq!(err.into_errors(), ["unavailable"; 3]);
+    /// # }
+    /// ```
+    ///
+    /// Every failed call but the last one is reported to the hooks:
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::time::Duration;
+    /// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+    /// # async fn main() {
+    /// let started = tokio::time::Instant::now();
+    /// let mut retries = Vec::new();
+    /// let err = Attempt::to(|| async {
+    ///     tokio::time::sleep(Duration::from_secs(5)).await;
+    ///     Err::<(), _>("unavailable")
+    /// })
+    /// .delay(Duration::from_secs(10))
+    /// .delay_growth_magnitude(1.0)
+    /// .max_tries(3)
+    /// .on_retry(|_: &&str, attempt, delay| retries.push((attempt, delay)))
+    /// # .sleeper(tokio::time::sleep)
+    /// .run_hedged_detailed(Duration::from_secs(1), 2)
+    /// .await
+    /// .unwrap_err();
+    ///
+    /// // The first call fails after 5s and is replaced 10s later, while the hedge started after
+    /// // 1s leaves it to that replacement when it fails.
+    /// assert_eq!(err.attempts(), 3);
+    /// assert_eq!(
+    ///     retries,
+    ///     [(1, Duration::from_secs(10)), (2, Duration::ZERO)],
+    /// );
+    /// assert_eq!(started.elapsed(), Duration::from_secs(20));
+    /// # }
+    /// ```
+    #[cfg(feature = "async-core")]
+    pub async fn run_hedged_detailed<Fut, T, E>(
+        mut self,
+        hedge_delay: Duration,
+        max_in_flight: usize,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: Operation<E, Output = Fut>,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+        Z: Sleeper + Send + Sync,
+    {
+        /// What the hedged calls are waiting for.
+        enum Event<T, E> {
+            Completed(usize, trace::Span, Result<T, E>),
+            Hedge,
+            Retry,
+            Cancelled,
+        }
+
+        /// Why a call is about to be started.
+        enum Launch {
+            /// The first call, or the replacement of a failed one.
+            Call,
+
+            /// A hedge of the calls in flight, drawn from the budget.
+            Hedge,
+        }
+
+        assert!(!hedge_delay.is_zero());
+        assert!(max_in_flight > 0);
+
+        let started = self.now();
+        let mut errors = ErrorHistory::new(self.error_history);
+        let mut previous_delay = None;
+        let mut in_flight = hedge::InFlight::new();
+        let mut attempts = 0;
+        if let Some(budget) = &self.budget {
+            budget.deposit();
+        }
+
+        let run_span = trace::Span::run();
+
+        let mut launch = Some(Launch::Call);
+        let mut rejected = false;
+        let mut cancelled = false;
+        let mut hedge = None;
+        let mut retry: Option<std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>> =
+            None;
+        let mut cancel = self.cancel.as_ref().map(CancelToken::waiter);
+        loop {
+            if let Some(kind) = launch.take() {
+                match self.hooks.before_attempt(attempts + 1) {
+                    Ok(()) => {
+                        let withdrawn = match kind {
+                            Launch::Call => true,
+                            Launch::Hedge => {
+                                self.budget.as_ref().is_none_or(RetryBudget::try_withdraw)
+                            }
+                        };
+                        if withdrawn {
+                            attempts += 1;
+
+                            let span = run_span.attempt(attempts);
+                            let elapsed = self.elapsed(started);
+                            let call = span.in_scope(|| {
+                                self.func.call(&AttemptContext {
+                                    attempt: attempts,
+                                    elapsed,
+                                    previous_delay,
+                                    previous_error: errors.last(),
+                                    sleeper: Some(&self.sleeper),
+                                })
+                            });
+                            let call = span.instrument(call);
+                            in_flight.push((attempts, span), call);
+                        }
+                    }
+                    Err(err) => {
+                        errors.push(err);
+                        rejected = true;
+
+                        if in_flight.is_empty() {
+                            let reason = StopReason::Rejected;
+                            let err = errors.finish(attempts, self.elapsed(started), reason);
+                            run_span.record_outcome(Some(attempts), &reason);
+                            run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+
+            // Another call can be started as long as the hooks and limits allow it, and no
+            // replacement of a failed call is already on its way.
+            let can_start = !rejected
+                && !cancelled
+                && retry.is_none()
+                && self.max_tries.is_none_or(|max_tries| attempts < max_tries);
+
+            // The timer only runs while another call could be started when it fires.
+            if can_start && !in_flight.is_empty() && in_flight.len() < max_in_flight {
+                if hedge.is_none() {
+                    hedge = Some(self.sleeper.sleep(hedge_delay));
+                }
+            } else {
+                hedge = None;
+            }
+
+            let event = std::future::poll_fn(|cx| {
+                if let std::task::Poll::Ready(((attempt, span), res)) = in_flight.poll_next(cx) {
+                    return std::task::Poll::Ready(Event::Completed(attempt, span, res));
+                }
+                if let Some(std::task::Poll::Ready(())) =
+                    retry.as_mut().map(|retry| retry.as_mut().poll(cx))
+                {
+                    return std::task::Poll::Ready(Event::Retry);
+                }
+                if let Some(std::task::Poll::Ready(())) =
+                    cancel.as_mut().map(|cancel| cancel.poll_cancelled(cx))
+                {
+                    return std::task::Poll::Ready(Event::Cancelled);
+                }
+
+                match &mut hedge {
+                    Some(hedge) => hedge.as_mut().poll(cx).map(|()| Event::Hedge),
+                    None => std::task::Poll::Pending,
+                }
+            })
+            .await;
+
+            let (attempt, span, err) = match event {
+                Event::Completed(attempt, span, Ok(res)) => {
+                    span.record_outcome(None, &"success");
+                    run_span.record_outcome(Some(attempts), &"success");
+                    span.in_scope(|| self.hooks.on_success(attempt));
+
+                    return Ok(res);
+                }
+                Event::Completed(attempt, span, Err(err)) => (attempt, span, err),
+                Event::Hedge => {
+                    hedge = None;
+
+                    let can_hedge = self
+                        .deadline
+                        .is_none_or(|deadline| self.elapsed(started) < deadline);
+                    if can_hedge {
+                        launch = Some(Launch::Hedge);
+                    }
+
+                    continue;
+                }
+                Event::Retry => {
+                    retry = None;
+                    launch = Some(Launch::Call);
+
+                    continue;
+                }
+                Event::Cancelled => {
+                    cancel = None;
+                    cancelled = true;
+                    retry = None;
+
+                    // The calls in flight may still succeed, but nothing else is started.
+                    if !in_flight.is_empty() {
+                        continue;
+                    }
+
+                    let reason = StopReason::Cancelled;
+                    let err = errors.finish(attempts, self.elapsed(started), reason);
+                    run_span.record_outcome(Some(attempts), &reason);
+                    run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+                    return Err(err);
+                }
+            };
+
+            // The delay before the replacement of the failed call, or `None` if the calls in
+            // flight or the replacement already on its way take over.
+            let last = in_flight.is_empty() && retry.is_none();
+            let next = if can_start {
+                match span.in_scope(|| self.next_delay(attempt, &err, started)) {
+                    Ok(delay) => Ok(Some(delay)),
+                    Err(reason) if last || reason == StopReason::NonRetryable => Err(reason),
+                    Err(_) => Ok(None),
+                }
+            } else if let RetryDecision::Stop = self.retry_if.decide(&err) {
+                Err(StopReason::NonRetryable)
+            } else if !last {
+                Ok(None)
+            } else if cancelled {
+                Err(StopReason::Cancelled)
+            } else if rejected {
+                Err(StopReason::Rejected)
+            } else {
+                Err(StopReason::MaxTries)
+            };
+
+            let reason = match next {
+                Ok(delay) => {
+                    span.in_scope(|| {
+                        self.hooks
+                            .on_retry(&err, attempt, delay.unwrap_or(Duration::ZERO))
+                    });
+                    span.record_retry(delay.unwrap_or(Duration::ZERO));
+                    errors.push(err);
+
+                    match delay {
+                        Some(delay) if delay.is_zero() => launch = Some(Launch::Call),
+                        Some(delay) => retry = Some(self.sleeper.sleep(delay)),
+                        None => {}
+                    }
+                    if delay.is_some() {
+                        previous_delay = delay;
+                    }
+
+                    continue;
+                }
+                Err(reason) => {
+                    span.record_outcome(None, &reason);
+                    errors.push(err);
+
+                    reason
+                }
+            };
+
+            let err = errors.finish(attempts, self.elapsed(started), reason);
+            run_span.record_outcome(Some(attempts), &reason);
+            run_span.in_scope(|| self.hooks.on_give_up(&err));
+
+            return Err(err);
+        }
+    }
+}
+
+impl<F, P, R, S, G, Z, A> Attempt<F, P, Hooks<R, S, G>, Z, A> {
+    /// Sets a callback invoked after each failed call that is going to be retried, right before
+    /// sleeping. It receives the error, the number of the failed attempt (starting at 1) and the
+    /// delay about to be slept for.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::Attempt;
+    /// # use std::time::Duration;
+    /// let mut retries = Vec::new();
+    /// let mut succeeded_after = None;
+    /// let calls = std::cell::Cell::new(0);
+    ///
+    /// let res: Result<&str, String> = Attempt::to(|| {
+    ///     calls.set(calls.get() + 1);
+    ///     if calls.get() < 3 { Err(format!("failure #{}", calls.get())) } else { Ok("done") }
+    /// })
+    /// .delay(Duration::from_millis(1))
+    /// .delay_growth_magnitude(2.0)
+    /// .on_retry(|err: &String, attempt, delay| retries.push((err.clone(), attempt, delay)))
+    /// .on_success(|attempts| succeeded_after = Some(attempts))
+    /// .run();
+    ///
+    /// assert_eq!(res, Ok("done"));
+    /// assert_eq!(retries, [
+    ///     ("failure #1".to_string(), 1, Duration::from_millis(1)),
+    ///     ("failure #2".to_string(), 2, Duration::from_millis(2)),
+    /// ]);
+    /// assert_eq!(succeeded_after, Some(3));
+    /// ```
+    pub fn on_retry<E, Q>(self, on_retry: Q) -> Attempt<F, P, Hooks<Q, S, G>, Z, A>
+    where
+        Q: FnMut(&E, usize, Duration),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry,
+            on_success: hooks.on_success,
+            on_give_up: hooks.on_give_up,
+        })
+    }
+
+    /// Sets a callback invoked once the function returns [`Ok`], with the number of calls it
+    /// took.
+    pub fn on_success<Q>(self, on_success: Q) -> Attempt<F, P, Hooks<R, Q, G>, Z, A>
+    where
+        Q: FnMut(usize),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry: hooks.on_retry,
+            on_success,
+            on_give_up: hooks.on_give_up,
+        })
+    }
+
+    /// Sets a callback invoked once the [`Attempt`] stops retrying and is about to return an
+    /// error.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use attempt::{Attempt, StopReason};
+    /// let mut gave_up = None;
+    ///
+    /// let res: Result<(), &str> = Attempt::to(|| Err("nope"))
+    ///     .max_tries(3)
+    ///     .on_give_up(|err| gave_up = Some((*err.last(), err.attempts(), err.reason())))
+    ///     .run();
+    ///
+    /// assert_eq!(res, Err("nope"));
+    /// assert_eq!(gave_up, Some(("nope", 3, StopReason::MaxTries)));
+    /// ```
+    pub fn on_give_up<E, Q>(self, on_give_up: Q) -> Attempt<F, P, Hooks<R, S, Q>, Z, A>
+    where
+        Q: FnMut(&RetryError<E>),
+    {
+        self.map_hooks(|hooks| Hooks {
+            on_retry: hooks.on_retry,
+            on_success: hooks.on_success,
+            on_give_up,
+        })
+    }
 }
@@ -0,0 +1,131 @@
+//! Reporting of retries through the [metrics](https://docs.rs/metrics) facade.
+
+use std::time::{Duration, Instant};
+
+use ::metrics::{counter, histogram, SharedString};
+
+use crate::{AttemptHooks, RetryError};
+
+/// Hooks which report every run of an [`Attempt`](crate::Attempt) to the installed
+/// [metrics](https://docs.rs/metrics) recorder, before calling the wrapped hooks.
+///
+/// The following metrics are emitted, all labeled with `operation`, the name given to
+/// [`Attempt::metrics`](crate::Attempt::metrics):
+/// * `attempt_calls_total`: a counter of the calls made to the function.
+/// * `attempt_retries_total`: a counter of the failed calls which were retried.
+/// * `attempt_successes_total`: a counter of the runs which succeeded.
+/// * `attempt_give_ups_total`: a counter of the runs which failed, with the
+///   [`StopReason`](crate::StopReason) as the `reason` label.
+/// * `attempt_calls_per_run`: a histogram of the number of calls made by each run.
+/// * `attempt_duration_seconds`: a histogram of the time taken by each run, sleeps included.
+///
+/// # Example
+/// ```rust
+/// # use attempt::Attempt;
+/// # use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+/// # use std::collections::HashMap;
+/// let recorder = DebuggingRecorder::new();
+/// let snapshotter = recorder.snapshotter();
+///
+/// metrics::with_local_recorder(&recorder, || {
+///     let mut calls = 0;
+///     Attempt::to(|| {
+///         calls += 1;
+///         if calls < 3 { Err("unavailable") } else { Ok(()) }
+///     })
+///     .metrics("fetch_user")
+///     .run()
+///     .unwrap();
+///
+///     Attempt::to(|| Err::<(), _>("not found"))
+///         .retry_if(|_: &&str| false)
+///         .metrics("fetch_user")
+///         .run()
+///         .unwrap_err();
+/// });
+///
+/// let metrics: HashMap<_, _> = snapshotter
+///     .snapshot()
+///     .into_vec()
+///     .into_iter()
+///     .map(|(key, _, _, value)| (key.key().name().to_string(), (key, value)))
+///     .collect();
+///
+/// let (key, calls) = &metrics["attempt_calls_total"];
+/// assert_eq!(key.key().labels().next().unwrap().value(), "fetch_user");
+/// assert_eq!(*calls, DebugValue::Counter(4));
+/// assert_eq!(metrics["attempt_retries_total"].1, DebugValue::Counter(2));
+/// assert_eq!(metrics["attempt_successes_total"].1, DebugValue::Counter(1));
+/// assert_eq!(metrics["attempt_give_ups_total"].1, DebugValue::Counter(1));
+///
+/// let DebugValue::Histogram(calls_per_run) = &metrics["attempt_calls_per_run"].1 else {
+///     unreachable!()
+/// };
+/// let calls_per_run: Vec<f64> = calls_per_run.iter().map(|calls| calls.0).collect();
+/// assert_eq!(calls_per_run, [3.0, 1.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithMetrics<H> {
+    hooks: H,
+    operation: SharedString,
+
+    /// When the first call of the run was about to be made.
+    started: Option<Instant>,
+}
+
+impl<H> WithMetrics<H> {
+    pub(crate) fn new(hooks: H, operation: SharedString) -> WithMetrics<H> {
+        WithMetrics {
+            hooks,
+            operation,
+            started: None,
+        }
+    }
+
+    /// Records the end of a run which made `attempts` calls.
+    fn finish(&mut self, attempts: usize) {
+        let operation = self.operation.clone();
+        counter!("attempt_calls_total", "operation" => operation.clone())
+            .increment(attempts as u64);
+        histogram!("attempt_calls_per_run", "operation" => operation.clone())
+            .record(attempts as f64);
+
+        let elapsed = self
+            .started
+            .take()
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        histogram!("attempt_duration_seconds", "operation" => operation).record(elapsed);
+    }
+}
+
+impl<E, H> AttemptHooks<E> for WithMetrics<H>
+where
+    H: AttemptHooks<E>,
+{
+    fn before_attempt(&mut self, attempt: usize) -> Result<(), E> {
+        self.started.get_or_insert_with(Instant::now);
+        self.hooks.before_attempt(attempt)
+    }
+
+    fn on_retry(&mut self, err: &E, attempt: usize, delay: Duration) {
+        counter!("attempt_retries_total", "operation" => self.operation.clone()).increment(1);
+        self.hooks.on_retry(err, attempt, delay);
+    }
+
+    fn on_success(&mut self, attempts: usize) {
+        counter!("attempt_successes_total", "operation" => self.operation.clone()).increment(1);
+        self.finish(attempts);
+        self.hooks.on_success(attempts);
+    }
+
+    fn on_give_up(&mut self, err: &RetryError<E>) {
+        counter!(
+            "attempt_give_ups_total",
+            "operation" => self.operation.clone(),
+            "reason" => err.reason().to_string(),
+        )
+        .increment(1);
+        self.finish(err.attempts());
+        self.hooks.on_give_up(err);
+    }
+}
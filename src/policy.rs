@@ -0,0 +1,199 @@
+//! Retry configuration which can be defined once and applied to many functions.
+
+use std::time::Duration;
+
+use crate::{Attempt, Jitter, DEFAULT_DELAY_GROWTH, DEFAULT_MAX_TRIES};
+
+/// The configuration of an [`Attempt`], without the function being retried.
+///
+/// A policy can be defined once, e.g. when loading the configuration of a service, and applied
+/// to any number of functions with [`RetryPolicy::to`] or [`Attempt::policy`]. Its defaults are
+/// the same as the ones of [`Attempt::to`].
+///
+/// With the `serde` feature, the policy can be serialized and deserialized. Durations are written
+/// in a human-friendly format like `"500ms"` or `"1m 30s"`, and missing fields keep their default
+/// value. Note that formats without null values, like TOML, can't express a policy with
+/// [`RetryPolicy::no_max_tries`].
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Jitter, RetryPolicy};
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::new()
+///     .delay(Duration::from_millis(1))
+///     .max_tries(3)
+///     .jitter(Jitter::Full);
+///
+/// let mut calls = 0;
+/// let res: Result<(), _> = policy
+///     .to(|| {
+///         calls += 1;
+///         Err("unavailable")
+///     })
+///     .run();
+/// assert_eq!(res, Err("unavailable"));
+/// assert_eq!(calls, 3);
+///
+/// let res = policy.to(|| Ok::<_, ()>("done")).run();
+/// assert_eq!(res, Ok("done"));
+/// ```
+///
+/// Loading a policy from a configuration file:
+/// ```rust
+/// # use attempt::{Jitter, RetryPolicy};
+/// # use std::time::Duration;
+/// # #[cfg(feature = "serde")]
+/// # {
+/// let policy: RetryPolicy = toml::from_str(r#"
+///     delay = "100ms"
+///     delay_growth_magnitude = 2.0
+///     max_delay = "1m 30s"
+///     jitter = "equal"
+/// "#)
+/// .unwrap();
+///
+/// assert_eq!(
+///     policy,
+///     RetryPolicy::new()
+///         .delay(Duration::from_millis(100))
+///         .delay_growth_magnitude(2.0)
+///         .max_delay(Duration::from_secs(90))
+///         .jitter(Jitter::Equal),
+/// );
+///
+/// // Like `RetryPolicy::max_tries`, a policy must allow at least one call.
+/// assert!(toml::from_str::<RetryPolicy>("max_tries = 0").is_err());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct RetryPolicy {
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub(crate) delay: Duration,
+    pub(crate) delay_growth_magnitude: f32,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_max_tries"))]
+    pub(crate) max_tries: Option<usize>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) max_delay: Option<Duration>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "humantime_serde", skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) deadline: Option<Duration>,
+    pub(crate) jitter: Jitter,
+}
+
+/// Deserializes [`RetryPolicy::max_tries`], rejecting 0 like the setter does.
+#[cfg(feature = "serde")]
+fn deserialize_max_tries<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::{Error, Unexpected};
+    use serde::Deserialize;
+
+    match Option::<usize>::deserialize(deserializer)? {
+        Some(0) => Err(D::Error::invalid_value(
+            Unexpected::Unsigned(0),
+            &"a number of tries greater than 0",
+        )),
+        max_tries => Ok(max_tries),
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            delay: Duration::ZERO,
+            delay_growth_magnitude: DEFAULT_DELAY_GROWTH,
+            max_tries: Some(DEFAULT_MAX_TRIES),
+            max_delay: None,
+            deadline: None,
+            jitter: Jitter::None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Constructs a new policy with the default configuration outlined in the documentation for
+    /// [`Attempt::to`].
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Constructs an [`Attempt`] which retries `func` following this policy.
+    ///
+    /// The [`Attempt`] can be configured further before running it, without affecting the
+    /// policy.
+    pub fn to<F>(&self, func: F) -> Attempt<F> {
+        Attempt::to(func).policy(self)
+    }
+
+    /// Removes the limit on the number of calls. See [`Attempt::no_max_tries`].
+    pub fn no_max_tries(mut self) -> Self {
+        self.max_tries = None;
+
+        self
+    }
+
+    /// Sets the maximum number of calls. See [`Attempt::max_tries`].
+    ///
+    /// Must be greater than 0 (checked by assertion).
+    pub fn max_tries(mut self, max_tries: usize) -> Self {
+        assert!(max_tries > 0);
+        self.max_tries = Some(max_tries);
+
+        self
+    }
+
+    /// Removes the delay between calls. See [`Attempt::no_delay`].
+    pub fn no_delay(mut self) -> Self {
+        self.delay = Duration::ZERO;
+
+        self
+    }
+
+    /// Sets the delay before the first retry. See [`Attempt::delay`].
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+
+        self
+    }
+
+    /// Sets the factor the delay is multiplied by after each retry. See
+    /// [`Attempt::delay_growth_magnitude`].
+    pub fn delay_growth_magnitude(mut self, magnitude: f32) -> Self {
+        self.delay_growth_magnitude = magnitude;
+
+        self
+    }
+
+    /// Caps every delay at `max_delay`. See [`Attempt::max_delay`].
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+
+        self
+    }
+
+    /// Stops retrying once `deadline` has passed since the first call. See
+    /// [`Attempt::deadline`].
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+
+        self
+    }
+
+    /// Sets how the delays are randomized. See [`Attempt::jitter`].
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+
+        self
+    }
+}
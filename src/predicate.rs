@@ -0,0 +1,105 @@
+//! Classification of errors into ones worth retrying and ones that should be returned
+//! immediately.
+
+use std::time::Duration;
+
+/// The verdict of a [`RetryPredicate`] for a single error value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// The error is transient, so the function should be called again (subject to the other
+    /// limits configured on the [`Attempt`](crate::Attempt)).
+    Retry,
+
+    /// The error is transient, but the function shouldn't be called again before at least the
+    /// given duration has passed. The delay is still capped by
+    /// [`Attempt::max_delay`](crate::Attempt::max_delay).
+    RetryAfter(Duration),
+
+    /// The error is permanent, so it should be returned right away without sleeping or calling
+    /// the function again.
+    Stop,
+}
+
+impl From<bool> for RetryDecision {
+    /// `true` maps to [`RetryDecision::Retry`] and `false` maps to [`RetryDecision::Stop`].
+    fn from(retry: bool) -> Self {
+        if retry {
+            RetryDecision::Retry
+        } else {
+            RetryDecision::Stop
+        }
+    }
+}
+
+/// Decides, per error value, whether a failed call should be retried.
+///
+/// This trait is implemented for any closure of the form `FnMut(&E) -> R` where `R` is either a
+/// [`RetryDecision`] or a [`bool`], so most of the time there's no need to implement it by hand.
+pub trait RetryPredicate<E> {
+    /// Classifies `err`, the error returned by the most recent call to the function.
+    fn decide(&mut self, err: &E) -> RetryDecision;
+}
+
+impl<E, F, R> RetryPredicate<E> for F
+where
+    F: FnMut(&E) -> R,
+    R: Into<RetryDecision>,
+{
+    fn decide(&mut self, err: &E) -> RetryDecision {
+        self(err).into()
+    }
+}
+
+/// The default [`RetryPredicate`], which treats every error as transient.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysRetry;
+
+impl<E> RetryPredicate<E> for AlwaysRetry {
+    fn decide(&mut self, _err: &E) -> RetryDecision {
+        RetryDecision::Retry
+    }
+}
+
+/// Implemented by errors which can tell how long to wait before trying again, like HTTP 429 or
+/// 503 responses carrying a `Retry-After` header.
+///
+/// The hint is only used when enabled with
+/// [`Attempt::honor_retry_after`](crate::Attempt::honor_retry_after).
+pub trait RetryAfter {
+    /// Returns the minimum delay to wait before the next attempt, or [`None`] to use the
+    /// configured schedule.
+    fn retry_after(&self) -> Option<Duration>;
+}
+
+/// Decides whether the delay requested by an error through [`RetryAfter`] is waited for.
+///
+/// This trait is implemented for [`IgnoreRetryAfter`], the default, and for
+/// [`HonorRetryAfter`], set with [`Attempt::honor_retry_after`](crate::Attempt::honor_retry_after).
+pub trait RetryAfterMode<E> {
+    /// Returns the minimum delay to wait after `err` before the next attempt, or [`None`] to use
+    /// the configured schedule.
+    fn min_delay(&self, err: &E) -> Option<Duration>;
+}
+
+/// The default [`RetryAfterMode`], which uses the configured schedule whatever the error
+/// requests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IgnoreRetryAfter;
+
+impl<E> RetryAfterMode<E> for IgnoreRetryAfter {
+    fn min_delay(&self, _err: &E) -> Option<Duration> {
+        None
+    }
+}
+
+/// A [`RetryAfterMode`] which asks retryable errors how long to wait through [`RetryAfter`].
+///
+/// See [`Attempt::honor_retry_after`](crate::Attempt::honor_retry_after).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HonorRetryAfter;
+
+impl<E: RetryAfter> RetryAfterMode<E> for HonorRetryAfter {
+    fn min_delay(&self, err: &E) -> Option<Duration> {
+        err.retry_after()
+    }
+}
@@ -0,0 +1,318 @@
+//! Retrying of iterators and streams, resuming from the last item instead of starting over.
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async-core")]
+use std::future::Future;
+#[cfg(feature = "async-core")]
+use std::pin::Pin;
+#[cfg(feature = "async-core")]
+use std::task::{ready, Context, Poll};
+
+#[cfg(feature = "async-core")]
+use crate::cancel::CancelWaiter;
+use crate::error::ErrorHistory;
+#[cfg(feature = "async-core")]
+use crate::Sleeper;
+use crate::{
+    Attempt, AttemptHooks, DefaultSleeper, IgnoreRetryAfter, RetryAfterMode, RetryPredicate,
+    StopReason,
+};
+
+/// The state shared by [`ResumeIter`] and [`ResumeStream`].
+struct Resume<F, P, H, T, Z, A> {
+    attempt: Attempt<F, P, H, Z, A>,
+
+    /// The last item yielded, which the source is re-created from.
+    checkpoint: Option<T>,
+
+    /// The number of times the source failed since the last item.
+    failures: usize,
+
+    /// When the last item was yielded, or when iteration started if none was.
+    started: Instant,
+
+    /// Whether the source was (re-)created and didn't yield anything yet.
+    fresh: bool,
+
+    done: bool,
+}
+
+impl<F, P, H, T, Z, A> Resume<F, P, H, T, Z, A> {
+    fn new(attempt: Attempt<F, P, H, Z, A>) -> Resume<F, P, H, T, Z, A> {
+        if let Some(budget) = &attempt.budget {
+            budget.deposit();
+        }
+
+        Resume {
+            started: attempt.now(),
+            attempt,
+            checkpoint: None,
+            failures: 0,
+            fresh: false,
+            done: false,
+        }
+    }
+
+    /// Creates the source, starting after the checkpoint if there is one.
+    fn create<S, E>(&mut self) -> Result<S, E>
+    where
+        F: FnMut(Option<&T>) -> S,
+        H: AttemptHooks<E>,
+    {
+        if let Err(err) = self.attempt.hooks.before_attempt(self.failures + 1) {
+            return Err(self.give_up(err, StopReason::Rejected));
+        }
+
+        self.fresh = true;
+
+        Ok((self.attempt.func)(self.checkpoint.as_ref()))
+    }
+
+    /// Records `item` as the new checkpoint before it is yielded.
+    fn progress<E>(&mut self, item: T) -> T
+    where
+        H: AttemptHooks<E>,
+        T: Clone,
+    {
+        if self.fresh {
+            self.attempt.hooks.on_success(self.failures + 1);
+            self.fresh = false;
+        }
+
+        self.failures = 0;
+        self.started = self.attempt.now();
+        self.checkpoint = Some(item.clone());
+
+        item
+    }
+
+    /// Handles the end of the source.
+    fn finish<E>(&mut self)
+    where
+        H: AttemptHooks<E>,
+    {
+        if self.fresh {
+            self.attempt.hooks.on_success(self.failures + 1);
+        }
+
+        self.done = true;
+    }
+
+    /// Decides what happens after the source failed with `err`, returning the delay to sleep for
+    /// before re-creating it or the reason to give up.
+    fn fail<E>(&mut self, err: &E) -> Result<Duration, StopReason>
+    where
+        P: RetryPredicate<E>,
+        A: RetryAfterMode<E>,
+        H: AttemptHooks<E>,
+    {
+        self.fresh = false;
+        self.failures += 1;
+
+        let next = self.attempt.next_delay(self.failures, err, self.started);
+        if let Ok(delay) = next {
+            self.attempt.hooks.on_retry(err, self.failures, delay);
+        }
+
+        next
+    }
+
+    /// Stops iterating, returning the error to yield last.
+    fn give_up<E>(&mut self, err: E, reason: StopReason) -> E
+    where
+        H: AttemptHooks<E>,
+    {
+        self.done = true;
+
+        let mut errors = ErrorHistory::new(Some(1));
+        errors.push(err);
+        let err = errors.finish(self.failures, self.attempt.elapsed(self.started), reason);
+        self.attempt.hooks.on_give_up(&err);
+
+        err.into_last()
+    }
+}
+
+/// An [`Iterator`] which re-creates its source when it fails, starting after the last item it
+/// yielded.
+///
+/// See [`Attempt::resume_iter`].
+pub struct ResumeIter<F, P, H, S, T, Z = DefaultSleeper, A = IgnoreRetryAfter>
+where
+    S: IntoIterator,
+{
+    resume: Resume<F, P, H, T, Z, A>,
+    source: Option<S::IntoIter>,
+}
+
+impl<F, P, H, S, T, Z, A> ResumeIter<F, P, H, S, T, Z, A>
+where
+    S: IntoIterator,
+{
+    pub(crate) fn new(attempt: Attempt<F, P, H, Z, A>) -> ResumeIter<F, P, H, S, T, Z, A> {
+        ResumeIter {
+            resume: Resume::new(attempt),
+            source: None,
+        }
+    }
+}
+
+impl<F, P, H, S, T, E, Z, A> Iterator for ResumeIter<F, P, H, S, T, Z, A>
+where
+    F: FnMut(Option<&T>) -> S,
+    S: IntoIterator<Item = Result<T, E>>,
+    P: RetryPredicate<E>,
+    A: RetryAfterMode<E>,
+    H: AttemptHooks<E>,
+    T: Clone,
+{
+    type Item = Result<T, E>;
+
+    fn next(&mut self) -> Option<Result<T, E>> {
+        loop {
+            if self.resume.done {
+                return None;
+            }
+
+            let source = match &mut self.source {
+                Some(source) => source,
+                None => match self.resume.create::<S, E>() {
+                    Ok(source) => self.source.insert(source.into_iter()),
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+
+            let err = match source.next() {
+                Some(Ok(item)) => return Some(Ok(self.resume.progress(item))),
+                Some(Err(err)) => err,
+                None => {
+                    self.resume.finish();
+
+                    return None;
+                }
+            };
+
+            self.source = None;
+            let reason = match self.resume.fail(&err) {
+                Ok(delay) => {
+                    if self.resume.attempt.sleep(delay) {
+                        continue;
+                    }
+
+                    StopReason::Cancelled
+                }
+                Err(reason) => reason,
+            };
+
+            return Some(Err(self.resume.give_up(err, reason)));
+        }
+    }
+}
+
+/// A [`Stream`](futures_core::Stream) which re-creates its source when it fails, starting after
+/// the last item it yielded.
+///
+/// See [`Attempt::resume_stream`].
+#[cfg(feature = "async-core")]
+pub struct ResumeStream<F, P, H, S, T, E, Z = DefaultSleeper, A = IgnoreRetryAfter> {
+    resume: Resume<F, P, H, T, Z, A>,
+    source: Option<Pin<Box<S>>>,
+    sleep: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+
+    /// The registration of the ongoing sleep with the [`CancelToken`](crate::CancelToken).
+    cancel_waiter: Option<CancelWaiter>,
+
+    /// The error which caused the ongoing sleep, returned if the [`Attempt`] is cancelled.
+    pending: Option<E>,
+}
+
+#[cfg(feature = "async-core")]
+impl<F, P, H, S, T, E, Z, A> ResumeStream<F, P, H, S, T, E, Z, A> {
+    pub(crate) fn new(attempt: Attempt<F, P, H, Z, A>) -> ResumeStream<F, P, H, S, T, E, Z, A> {
+        ResumeStream {
+            resume: Resume::new(attempt),
+            source: None,
+            sleep: None,
+            cancel_waiter: None,
+            pending: None,
+        }
+    }
+}
+
+// The source is boxed, so none of the fields are ever pinned.
+#[cfg(feature = "async-core")]
+impl<F, P, H, S, T, E, Z, A> Unpin for ResumeStream<F, P, H, S, T, E, Z, A> {}
+
+#[cfg(feature = "async-core")]
+impl<F, P, H, S, T, E, Z, A> futures_core::Stream for ResumeStream<F, P, H, S, T, E, Z, A>
+where
+    Z: Sleeper,
+    F: FnMut(Option<&T>) -> S,
+    S: futures_core::Stream<Item = Result<T, E>>,
+    P: RetryPredicate<E>,
+    A: RetryAfterMode<E>,
+    H: AttemptHooks<E>,
+    T: Clone,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<T, E>>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.resume.done {
+                return Poll::Ready(None);
+            }
+
+            if let Some(sleep) = &mut this.sleep {
+                if let Some(token) = &this.resume.attempt.cancel {
+                    let waiter = this.cancel_waiter.get_or_insert_with(|| token.waiter());
+                    if waiter.poll_cancelled(cx).is_ready() {
+                        this.sleep = None;
+                        this.cancel_waiter = None;
+                        let err = this.pending.take().expect("sleeping after an error");
+
+                        return Poll::Ready(Some(Err(this
+                            .resume
+                            .give_up(err, StopReason::Cancelled))));
+                    }
+                }
+
+                ready!(sleep.as_mut().poll(cx));
+                this.sleep = None;
+                this.cancel_waiter = None;
+                this.pending = None;
+            }
+
+            let source = match &mut this.source {
+                Some(source) => source,
+                None => match this.resume.create::<S, E>() {
+                    Ok(source) => this.source.insert(Box::pin(source)),
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                },
+            };
+
+            let err = match ready!(source.as_mut().poll_next(cx)) {
+                Some(Ok(item)) => return Poll::Ready(Some(Ok(this.resume.progress(item)))),
+                Some(Err(err)) => err,
+                None => {
+                    this.resume.finish();
+
+                    return Poll::Ready(None);
+                }
+            };
+
+            this.source = None;
+            match this.resume.fail(&err) {
+                Ok(delay) => {
+                    if !delay.is_zero() {
+                        this.sleep = Some(this.resume.attempt.sleeper.sleep(delay));
+                        this.pending = Some(err);
+                    }
+                }
+                Err(reason) => return Poll::Ready(Some(Err(this.resume.give_up(err, reason)))),
+            }
+        }
+    }
+}
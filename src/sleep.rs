@@ -0,0 +1,210 @@
+//! Runtime-agnostic sleeping for [`Attempt::run_async`](crate::Attempt::run_async).
+
+#[cfg(feature = "async-core")]
+use std::future::Future;
+#[cfg(feature = "async-core")]
+use std::pin::Pin;
+#[cfg(feature = "async-core")]
+use std::task::Poll;
+#[cfg(feature = "async-core")]
+use std::time::Duration;
+
+#[cfg(feature = "async-core")]
+use crate::CancelToken;
+
+/// Puts the retry loop of [`Attempt::run_async`](crate::Attempt::run_async) to sleep between
+/// attempts.
+///
+/// Implementations are provided for [tokio](TokioSleeper), [async-std](AsyncStdSleeper) and
+/// [smol](SmolSleeper) behind the cargo features of the same names, and for any closure of the
+/// form `Fn(Duration) -> impl Future<Output = ()>`, so any other runtime can be plugged in with
+/// [`Attempt::sleeper`](crate::Attempt::sleeper).
+///
+/// # Example
+/// ```rust
+/// # use attempt::Attempt;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # futures::executor::block_on(async {
+/// let slept = Arc::new(AtomicUsize::new(0));
+/// let counter = slept.clone();
+///
+/// let res: Result<(), ()> = Attempt::to(|| async { Err(()) })
+///     .delay(Duration::from_secs(60))
+///     .max_tries(3)
+///     // Instead of actually sleeping, just count the sleeps.
+///     .sleeper(move |_delay| {
+///         counter.fetch_add(1, Ordering::SeqCst);
+///         std::future::ready(())
+///     })
+///     .run_async()
+///     .await;
+///
+/// assert!(res.is_err());
+/// assert_eq!(slept.load(Ordering::SeqCst), 2);
+/// # });
+/// ```
+#[cfg(feature = "async-core")]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a `Sleeper`",
+    note = "without the `tokio`, `async-std` or `smol` feature, a `Sleeper` must be set with \
+            `Attempt::sleeper` before running asynchronously"
+)]
+pub trait Sleeper {
+    /// Returns a future which completes once `duration` has passed.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+}
+
+#[cfg(feature = "async-core")]
+impl<F, Fut> Sleeper for F
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(self(duration))
+    }
+}
+
+/// A [`Sleeper`] which uses [`tokio::time::sleep`].
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSleeper;
+
+#[cfg(feature = "tokio")]
+impl Sleeper for TokioSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A [`Sleeper`] which uses [`async_std::task::sleep`].
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, AsyncStdSleeper};
+/// # use std::time::Duration;
+/// # async_std::task::block_on(async {
+/// let res: Result<(), ()> = Attempt::to(|| async { Err(()) })
+///     .delay(Duration::from_millis(1))
+///     .max_tries(2)
+///     .sleeper(AsyncStdSleeper)
+///     .run_async()
+///     .await;
+///
+/// assert!(res.is_err());
+/// # });
+/// ```
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdSleeper;
+
+#[cfg(feature = "async-std")]
+impl Sleeper for AsyncStdSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// A [`Sleeper`] which uses the timers of [`async_io`], the reactor behind smol.
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, SmolSleeper};
+/// # use std::time::Duration;
+/// # async_io::block_on(async {
+/// let res: Result<(), ()> = Attempt::to(|| async { Err(()) })
+///     .delay(Duration::from_millis(1))
+///     .max_tries(2)
+///     .sleeper(SmolSleeper)
+///     .run_async()
+///     .await;
+///
+/// assert!(res.is_err());
+/// # });
+/// ```
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolSleeper;
+
+#[cfg(feature = "smol")]
+impl Sleeper for SmolSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            async_io::Timer::after(duration).await;
+        })
+    }
+}
+
+/// The [`Sleeper`] of an [`Attempt`](crate::Attempt) until another one is set with
+/// [`Attempt::sleeper`](crate::Attempt::sleeper), which sleeps using the runtime selected
+/// through cargo features, preferring tokio, then async-std, then smol.
+///
+/// Without any of these features, this doesn't implement [`Sleeper`], so
+/// [`Attempt::run_async`](crate::Attempt::run_async) doesn't compile until a [`Sleeper`] is set:
+#[cfg_attr(
+    not(any(feature = "tokio", feature = "async-std", feature = "smol")),
+    doc = "```compile_fail"
+)]
+#[cfg_attr(
+    any(feature = "tokio", feature = "async-std", feature = "smol"),
+    doc = "```rust"
+)]
+/// # use attempt::Attempt;
+/// let res = Attempt::to(|| async { Err::<(), _>("unavailable") }).run_async();
+/// # drop(res);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSleeper;
+
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+impl Sleeper for DefaultSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        #[cfg(feature = "tokio")]
+        return TokioSleeper.sleep(duration);
+
+        #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+        return AsyncStdSleeper.sleep(duration);
+
+        #[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+        return SmolSleeper.sleep(duration);
+    }
+}
+
+/// Sleeps for `delay` between two attempts using `sleeper`, waking up early if `token` is
+/// cancelled.
+///
+/// Returns `false` if the token was cancelled.
+#[cfg(feature = "async-core")]
+pub(crate) async fn sleep_until_cancelled(
+    sleeper: &(dyn Sleeper + Send + Sync),
+    token: Option<&CancelToken>,
+    delay: Duration,
+) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => {
+            if !delay.is_zero() {
+                sleeper.sleep(delay).await;
+            }
+
+            return true;
+        }
+    };
+
+    if delay.is_zero() {
+        return !token.is_cancelled();
+    }
+
+    let mut sleep = sleeper.sleep(delay);
+    let mut waiter = token.waiter();
+    std::future::poll_fn(|cx| {
+        if waiter.poll_cancelled(cx).is_ready() {
+            return Poll::Ready(false);
+        }
+
+        sleep.as_mut().poll(cx).map(|()| true)
+    })
+    .await
+}
@@ -0,0 +1,92 @@
+//! Statistics about how a run of an [`Attempt`](crate::Attempt) went.
+
+use std::time::Duration;
+
+/// Statistics about a run of an [`Attempt`](crate::Attempt), returned by
+/// [`Attempt::run_with_stats`](crate::Attempt::run_with_stats) and
+/// [`Attempt::run_async_with_stats`](crate::Attempt::run_async_with_stats) whether it succeeded
+/// or not.
+///
+/// Times are measured with the [`Clock`](crate::Clock) of the [`Attempt`](crate::Attempt).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, MockClock};
+/// # use std::time::Duration;
+/// let clock = MockClock::new();
+/// let call_clock = clock.clone();
+///
+/// let mut calls = 0;
+/// let (res, stats) = Attempt::to(|| {
+///     calls += 1;
+///     call_clock.advance(Duration::from_millis(10 * calls));
+///     if calls < 3 { Err("unavailable") } else { Ok(calls) }
+/// })
+/// .delay(Duration::from_millis(100))
+/// .delay_growth_magnitude(2.0)
+/// .clock(clock)
+/// .run_with_stats();
+///
+/// assert_eq!(res, Ok(3));
+/// assert_eq!(stats.attempts(), 3);
+/// assert_eq!(stats.durations(), [10, 20, 30].map(Duration::from_millis));
+/// assert_eq!(stats.delays(), [100, 200].map(Duration::from_millis));
+/// assert_eq!(stats.execution_time(), Duration::from_millis(60));
+/// assert_eq!(stats.sleep_time(), Duration::from_millis(300));
+/// assert_eq!(stats.elapsed(), Duration::from_millis(360));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttemptStats {
+    elapsed: Duration,
+    sleep_time: Duration,
+    durations: Vec<Duration>,
+    delays: Vec<Duration>,
+}
+
+impl AttemptStats {
+    /// Returns the number of calls made to the function.
+    pub fn attempts(&self) -> usize {
+        self.durations.len()
+    }
+
+    /// Returns the time between the start of the first call and the end of the run.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the time spent sleeping between calls.
+    ///
+    /// This can be less than the sum of [`AttemptStats::delays`] if the last sleep was cut short
+    /// by a [`CancelToken`](crate::CancelToken).
+    pub fn sleep_time(&self) -> Duration {
+        self.sleep_time
+    }
+
+    /// Returns the time spent in calls to the function.
+    pub fn execution_time(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+
+    /// Returns how long each call to the function took, in order.
+    pub fn durations(&self) -> &[Duration] {
+        &self.durations
+    }
+
+    /// Returns the delay chosen before each retry, in order.
+    pub fn delays(&self) -> &[Duration] {
+        &self.delays
+    }
+
+    /// Records a call which took `duration`, ending `elapsed` into the run.
+    pub(crate) fn record_attempt(&mut self, duration: Duration, elapsed: Duration) {
+        self.durations.push(duration);
+        self.elapsed = elapsed;
+    }
+
+    /// Records a sleep for `delay` which actually lasted `slept`, ending `elapsed` into the run.
+    pub(crate) fn record_sleep(&mut self, delay: Duration, slept: Duration, elapsed: Duration) {
+        self.delays.push(delay);
+        self.sleep_time += slept;
+        self.elapsed = elapsed;
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-attempt timeouts for [`Attempt::run_async`](crate::Attempt::run_async).
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{AttemptContext, Operation, RetryAfter};
+
+/// The error of an attempt made with a timeout set by
+/// [`Attempt::timeout`](crate::Attempt::timeout).
+///
+/// # Example
+/// ```rust
+/// # use attempt::{Attempt, TimeoutError};
+/// # use std::time::Duration;
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// let mut calls = 0;
+/// let err = Attempt::to(|| {
+///     calls += 1;
+///     let hang = calls == 1;
+///     async move {
+///         if hang {
+///             tokio::time::sleep(Duration::from_secs(3600)).await;
+///         }
+///         Err::<(), _>("busy")
+///     }
+/// })
+/// .timeout(Duration::from_secs(1))
+/// .max_tries(2)
+/// # .sleeper(tokio::time::sleep)
+/// .run_async_detailed()
+/// .await
+/// .unwrap_err();
+///
+/// assert_eq!(
+///     err.into_errors(),
+///     [TimeoutError::TimedOut(Duration::from_secs(1)), TimeoutError::Inner("busy")],
+/// );
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// The attempt didn't complete within the given duration and was cancelled.
+    TimedOut(Duration),
+
+    /// The attempt completed in time, but returned an error.
+    Inner(E),
+}
+
+impl<E> TimeoutError<E> {
+    /// Returns whether the attempt timed out.
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, TimeoutError::TimedOut(_))
+    }
+
+    /// Returns the error returned by the function, or [`None`] if the attempt timed out.
+    pub fn inner(&self) -> Option<&E> {
+        match self {
+            TimeoutError::TimedOut(_) => None,
+            TimeoutError::Inner(err) => Some(err),
+        }
+    }
+
+    /// Consumes this error, returning the error returned by the function, or [`None`] if the
+    /// attempt timed out.
+    pub fn into_inner(self) -> Option<E> {
+        match self {
+            TimeoutError::TimedOut(_) => None,
+            TimeoutError::Inner(err) => Some(err),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TimeoutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutError::TimedOut(timeout) => write!(f, "attempt timed out after {:?}", timeout),
+            TimeoutError::Inner(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E> std::error::Error for TimeoutError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TimeoutError::TimedOut(_) => None,
+            TimeoutError::Inner(err) => Some(err),
+        }
+    }
+}
+
+impl<E: RetryAfter> RetryAfter for TimeoutError<E> {
+    fn retry_after(&self) -> Option<Duration> {
+        self.inner().and_then(RetryAfter::retry_after)
+    }
+}
+
+/// An [`Operation`] which limits each call of an asynchronous function to a duration.
+///
+/// See [`Attempt::timeout`](crate::Attempt::timeout).
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout<F> {
+    func: F,
+    timeout: Duration,
+}
+
+impl<F> Timeout<F> {
+    pub(crate) fn new(func: F, timeout: Duration) -> Timeout<F> {
+        Timeout { func, timeout }
+    }
+}
+
+impl<E, F, Fut, T> Operation<TimeoutError<E>> for Timeout<F>
+where
+    F: Operation<E, Output = Fut>,
+    Fut: Future<Output = Result<T, E>>,
+{
+    type Output = TimeoutFuture<Fut>;
+
+    fn call(&mut self, cx: &AttemptContext<'_, TimeoutError<E>>) -> TimeoutFuture<Fut> {
+        let sleeper = cx
+            .sleeper
+            .expect("timeouts are only supported by Attempt::run_async");
+
+        TimeoutFuture {
+            future: Box::pin(self.func.call(&cx.map_error(TimeoutError::inner))),
+            timer: sleeper.sleep(self.timeout),
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// The future of a single call made by a [`Timeout`], which resolves to
+/// [`TimeoutError::TimedOut`] if the call takes too long.
+pub struct TimeoutFuture<Fut> {
+    future: Pin<Box<Fut>>,
+    timer: Pin<Box<dyn Future<Output = ()> + Send>>,
+    timeout: Duration,
+}
+
+impl<Fut> fmt::Debug for TimeoutFuture<Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutFuture")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Fut, T, E> Future for TimeoutFuture<Fut>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    type Output = Result<T, TimeoutError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(res) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(res.map_err(TimeoutError::Inner));
+        }
+
+        match self.timer.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(TimeoutError::TimedOut(self.timeout))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
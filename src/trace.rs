@@ -0,0 +1,204 @@
+//! Instrumentation of the retry loops with [tracing](https://docs.rs/tracing) spans, which
+//! compiles down to nothing without the `tracing` feature.
+
+use std::fmt;
+#[cfg(feature = "async-core")]
+use std::future::Future;
+use std::time::Duration;
+
+#[cfg(feature = "tracing")]
+use tracing::field::{debug, display, Empty};
+
+#[cfg(feature = "tracing")]
+use crate::{AttemptHooks, RetryError};
+
+/// The span of a run (named `retry`) or of a single call (named `attempt`).
+///
+/// The run span has an `attempts` field and an `outcome` field, either `success` or the
+/// [`StopReason`](crate::StopReason). The span of a call has an `attempt` field with its number,
+/// an `outcome` field (`success`, `retry` or the [`StopReason`](crate::StopReason)) and a
+/// `delay` field with the delay chosen before the next call, if any. Sleeps happen in the run
+/// span, between the spans of the calls. Both have an `error` field, filled in by
+/// [`TraceErrors`].
+#[derive(Debug, Clone)]
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Span {
+    /// Creates the span of a whole run.
+    pub(crate) fn run() -> Span {
+        Span {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("retry", attempts = Empty, outcome = Empty, error = Empty),
+        }
+    }
+
+    /// Creates the span of the `attempt`th call, as a child of this run span.
+    pub(crate) fn attempt(&self, attempt: usize) -> Span {
+        #[cfg(not(feature = "tracing"))]
+        let _ = attempt;
+
+        Span {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                parent: &self.span,
+                "attempt",
+                attempt,
+                delay = Empty,
+                outcome = Empty,
+                error = Empty,
+            ),
+        }
+    }
+
+    /// Calls `f` with this span entered.
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(f)
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            f()
+        }
+    }
+
+    /// Enters this span every time `future` is polled.
+    #[cfg(feature = "async-core")]
+    pub(crate) fn instrument<Fut: Future>(&self, future: Fut) -> impl Future<Output = Fut::Output> {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Instrument::instrument(future, self.span.clone())
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            future
+        }
+    }
+
+    /// Records that the call failed and will be retried after `delay`, and closes its span so
+    /// that the sleep isn't part of it.
+    pub(crate) fn record_retry(self, delay: Duration) {
+        #[cfg(feature = "tracing")]
+        self.span
+            .record("delay", debug(delay))
+            .record("outcome", display("retry"));
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = delay;
+    }
+
+    /// Records how the call or the run ended, along with the number of calls made for a run.
+    pub(crate) fn record_outcome(&self, attempts: Option<usize>, outcome: &dyn fmt::Display) {
+        #[cfg(feature = "tracing")]
+        {
+            if let Some(attempts) = attempts {
+                self.span.record("attempts", attempts);
+            }
+            self.span.record("outcome", display(outcome));
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (attempts, outcome);
+    }
+}
+
+/// Hooks which record the errors of the function on the spans opened with the `tracing`
+/// feature, before calling the wrapped hooks.
+///
+/// Errors are recorded using their [`Display`](fmt::Display) implementation, as the `error`
+/// field of the span of the call for errors which are retried, and of the span of the run for
+/// the error it ends with.
+///
+/// See [`Attempt::trace_errors`](crate::Attempt::trace_errors).
+///
+/// # Example
+/// The spans of [`Attempt::run_async`](crate::Attempt::run_async) are the same as the ones of
+/// [`Attempt::run`](crate::Attempt::run):
+#[cfg_attr(feature = "async-core", doc = "```rust")]
+#[cfg_attr(not(feature = "async-core"), doc = "```ignore")]
+/// # use attempt::Attempt;
+/// # use std::io::Write;
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// # use tracing_subscriber::fmt::format::FmtSpan;
+/// # #[derive(Clone, Default)]
+/// # struct Logs(Arc<Mutex<Vec<u8>>>);
+/// # impl Write for Logs {
+/// #     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+/// #         self.0.lock().unwrap().write(buf)
+/// #     }
+/// #     fn flush(&mut self) -> std::io::Result<()> {
+/// #         Ok(())
+/// #     }
+/// # }
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() {
+/// let logs = Logs::default();
+/// let writer = logs.clone();
+/// let subscriber = tracing_subscriber::fmt()
+///     .with_writer(move || writer.clone())
+///     .with_span_events(FmtSpan::CLOSE)
+///     .without_time()
+///     .with_ansi(false)
+///     .finish();
+/// let _guard = tracing::subscriber::set_default(subscriber);
+///
+/// let res = Attempt::to(|| async { Err::<(), _>("unavailable") })
+///     .delay(Duration::from_secs(1))
+///     .max_tries(2)
+///     .trace_errors()
+/// #   .sleeper(tokio::time::sleep)
+///     .run_async()
+///     .await;
+/// assert_eq!(res, Err("unavailable"));
+///
+/// let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+/// let spans: Vec<_> = logs.lines().collect();
+/// assert_eq!(spans.len(), 3);
+/// assert!(spans[0].contains("attempt{attempt=1 error=unavailable delay=1s outcome=retry}"));
+/// assert!(spans[1].contains("attempt{attempt=2 outcome=maximum tries reached}"));
+/// assert!(spans[2].contains("retry{attempts=2 outcome=maximum tries reached error=unavailable}"));
+/// # }
+/// ```
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct TraceErrors<H> {
+    hooks: H,
+}
+
+#[cfg(feature = "tracing")]
+impl<H> TraceErrors<H> {
+    pub(crate) fn new(hooks: H) -> TraceErrors<H> {
+        TraceErrors { hooks }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<E, H> AttemptHooks<E> for TraceErrors<H>
+where
+    E: fmt::Display,
+    H: AttemptHooks<E>,
+{
+    fn before_attempt(&mut self, attempt: usize) -> Result<(), E> {
+        self.hooks.before_attempt(attempt)
+    }
+
+    fn on_retry(&mut self, err: &E, attempt: usize, delay: Duration) {
+        tracing::Span::current().record("error", display(err));
+        self.hooks.on_retry(err, attempt, delay);
+    }
+
+    fn on_success(&mut self, attempts: usize) {
+        self.hooks.on_success(attempts);
+    }
+
+    fn on_give_up(&mut self, err: &RetryError<E>) {
+        tracing::Span::current().record("error", display(err.last()));
+        self.hooks.on_give_up(err);
+    }
+}
@@ -0,0 +1,275 @@
+//! Polling until the value returned by the function satisfies a condition.
+
+use std::fmt;
+#[cfg(feature = "async-core")]
+use std::future::Future;
+#[cfg(feature = "async-core")]
+use std::pin::Pin;
+#[cfg(feature = "async-core")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "async-core")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{AttemptContext, Operation, RetryAfter, RetryError, StopReason};
+
+/// The error of a single call made by [`Attempt::run_until`](crate::Attempt::run_until) or
+/// [`Attempt::run_async_until`](crate::Attempt::run_async_until), as seen by the predicate and
+/// the hooks.
+///
+/// A call whose value doesn't satisfy the condition counts as a failure, so that it is retried
+/// like any error. The value itself is kept aside, see [`RunUntilError::last_value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UntilError<E> {
+    /// The call succeeded, but its value didn't satisfy the condition.
+    Unsatisfied,
+
+    /// The call failed.
+    Failed(E),
+}
+
+impl<E> UntilError<E> {
+    /// Returns whether the call succeeded with a value which didn't satisfy the condition.
+    pub fn is_unsatisfied(&self) -> bool {
+        matches!(self, UntilError::Unsatisfied)
+    }
+
+    /// Returns the error returned by the function, or [`None`] if the call succeeded.
+    pub fn error(&self) -> Option<&E> {
+        match self {
+            UntilError::Unsatisfied => None,
+            UntilError::Failed(err) => Some(err),
+        }
+    }
+
+    /// Consumes this error, returning the error returned by the function, or [`None`] if the
+    /// call succeeded.
+    pub fn into_error(self) -> Option<E> {
+        match self {
+            UntilError::Unsatisfied => None,
+            UntilError::Failed(err) => Some(err),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for UntilError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UntilError::Unsatisfied => f.write_str("value doesn't satisfy the condition"),
+            UntilError::Failed(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E> std::error::Error for UntilError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UntilError::Unsatisfied => None,
+            UntilError::Failed(err) => Some(err),
+        }
+    }
+}
+
+impl<E: RetryAfter> RetryAfter for UntilError<E> {
+    fn retry_after(&self) -> Option<Duration> {
+        self.error().and_then(RetryAfter::retry_after)
+    }
+}
+
+/// The error returned by [`Attempt::run_until`](crate::Attempt::run_until) and
+/// [`Attempt::run_async_until`](crate::Attempt::run_async_until) when the
+/// [`Attempt`](crate::Attempt) gives up: the [`RetryError`] telling why, along with the last
+/// value returned by the function, if any.
+///
+/// Only the last error is kept in the [`RetryError`], unless
+/// [`Attempt::error_history`](crate::Attempt::error_history) says otherwise, so polling for a
+/// long time doesn't keep every call around.
+#[derive(Debug, Clone)]
+pub struct RunUntilError<T, E> {
+    error: RetryError<UntilError<E>>,
+    last_value: Option<T>,
+}
+
+impl<T, E> RunUntilError<T, E> {
+    pub(crate) fn new(error: RetryError<UntilError<E>>, last_value: Option<T>) -> Self {
+        RunUntilError { error, last_value }
+    }
+
+    /// Returns the last value returned by the function, even if later calls failed, or [`None`]
+    /// if every call failed.
+    pub fn last_value(&self) -> Option<&T> {
+        self.last_value.as_ref()
+    }
+
+    /// Consumes this error, returning the last value returned by the function, or [`None`] if
+    /// every call failed.
+    pub fn into_last_value(self) -> Option<T> {
+        self.last_value
+    }
+
+    /// Returns the number of calls made to the function.
+    pub fn attempts(&self) -> usize {
+        self.error.attempts()
+    }
+
+    /// Returns why the [`Attempt`](crate::Attempt) gave up.
+    pub fn reason(&self) -> StopReason {
+        self.error.reason()
+    }
+
+    /// Returns the errors of the failed calls.
+    pub fn error(&self) -> &RetryError<UntilError<E>> {
+        &self.error
+    }
+
+    /// Consumes this error, returning the errors of the failed calls.
+    pub fn into_error(self) -> RetryError<UntilError<E>> {
+        self.error
+    }
+}
+
+impl<T, E: fmt::Display> fmt::Display for RunUntilError<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<T, E> std::error::Error for RunUntilError<T, E>
+where
+    T: fmt::Debug,
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The condition of a run, along with the last value it was checked against.
+pub(crate) struct Condition<C, T> {
+    condition: C,
+    last_value: Option<T>,
+}
+
+impl<C, T> Condition<C, T> {
+    pub(crate) fn new(condition: C) -> Condition<C, T> {
+        Condition {
+            condition,
+            last_value: None,
+        }
+    }
+
+    /// Takes the last value which didn't satisfy the condition.
+    pub(crate) fn take_last_value(&mut self) -> Option<T> {
+        self.last_value.take()
+    }
+
+    /// Checks the result of a call, keeping its value aside if it doesn't satisfy the
+    /// condition.
+    fn check<E>(&mut self, res: Result<T, E>) -> Result<T, UntilError<E>>
+    where
+        C: FnMut(&T) -> bool,
+    {
+        match res {
+            Ok(value) if (self.condition)(&value) => Ok(value),
+            Ok(value) => {
+                self.last_value = Some(value);
+
+                Err(UntilError::Unsatisfied)
+            }
+            Err(err) => Err(UntilError::Failed(err)),
+        }
+    }
+}
+
+/// An [`Operation`] which turns values not satisfying a [`Condition`] into
+/// [`UntilError::Unsatisfied`].
+pub(crate) struct Until<'a, F, C, T> {
+    func: F,
+    condition: &'a mut Condition<C, T>,
+}
+
+impl<'a, F, C, T> Until<'a, F, C, T> {
+    pub(crate) fn new(func: F, condition: &'a mut Condition<C, T>) -> Until<'a, F, C, T> {
+        Until { func, condition }
+    }
+}
+
+impl<E, F, C, T> Operation<UntilError<E>> for Until<'_, F, C, T>
+where
+    F: Operation<E, Output = Result<T, E>>,
+    C: FnMut(&T) -> bool,
+{
+    type Output = Result<T, UntilError<E>>;
+
+    fn call(&mut self, cx: &AttemptContext<'_, UntilError<E>>) -> Self::Output {
+        let res = self.func.call(&cx.map_error(UntilError::error));
+
+        self.condition.check(res)
+    }
+}
+
+/// An [`Operation`] which turns the values of an asynchronous function not satisfying a
+/// [`Condition`] into [`UntilError::Unsatisfied`].
+#[cfg(feature = "async-core")]
+pub(crate) struct UntilAsync<F, C, T> {
+    func: F,
+
+    /// Shared with the future of every call, which checks the value once it resolves.
+    condition: Arc<Mutex<Condition<C, T>>>,
+}
+
+#[cfg(feature = "async-core")]
+impl<F, C, T> UntilAsync<F, C, T> {
+    pub(crate) fn new(func: F, condition: Arc<Mutex<Condition<C, T>>>) -> UntilAsync<F, C, T> {
+        UntilAsync { func, condition }
+    }
+}
+
+#[cfg(feature = "async-core")]
+impl<E, F, C, Fut, T> Operation<UntilError<E>> for UntilAsync<F, C, T>
+where
+    F: Operation<E, Output = Fut>,
+    Fut: Future<Output = Result<T, E>>,
+    C: FnMut(&T) -> bool,
+{
+    type Output = UntilFuture<Fut, C, T>;
+
+    fn call(&mut self, cx: &AttemptContext<'_, UntilError<E>>) -> UntilFuture<Fut, C, T> {
+        UntilFuture {
+            future: Box::pin(self.func.call(&cx.map_error(UntilError::error))),
+            condition: Arc::clone(&self.condition),
+        }
+    }
+}
+
+/// The future of a single call made by an [`UntilAsync`].
+#[cfg(feature = "async-core")]
+pub(crate) struct UntilFuture<Fut, C, T> {
+    future: Pin<Box<Fut>>,
+    condition: Arc<Mutex<Condition<C, T>>>,
+}
+
+#[cfg(feature = "async-core")]
+impl<Fut, C, T, E> Future for UntilFuture<Fut, C, T>
+where
+    Fut: Future<Output = Result<T, E>>,
+    C: FnMut(&T) -> bool,
+{
+    type Output = Result<T, UntilError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match self.future.as_mut().poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let mut condition = self
+            .condition
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        Poll::Ready(condition.check(res))
+    }
+}